@@ -0,0 +1,164 @@
+//! Arbitrary output variables, images of what camera rays hit first besides its color.
+//!
+//! Denoisers and compositing use them to tell surfaces apart where the color alone can't.
+
+use std::str::FromStr;
+
+use anyhow::{bail, Error};
+
+use crate::{
+    hittable::HitRecord,
+    integrator::World,
+    material::{Material, MaterialT},
+    util::{color::Color, color::Transfer, interval::Interval, random::mix64, ray::Ray},
+};
+
+/// The AOVs a render can output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    /// Overall color of the material.
+    Albedo,
+    /// Shading normal, facing the camera.
+    Normal,
+    /// Point in the world.
+    Position,
+    /// Distance from the camera.
+    Depth,
+    /// Which top level object of the scene, numbered from 1.
+    ObjectId,
+    /// Which named material of the scene, numbered from 1 in the order of their names.
+    MaterialId,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 6] = [
+        Aov::Albedo,
+        Aov::Normal,
+        Aov::Position,
+        Aov::Depth,
+        Aov::ObjectId,
+        Aov::MaterialId,
+    ];
+
+    /// Name of the AOV in file and layer names.
+    pub fn name(self) -> &'static str {
+        match self {
+            Aov::Albedo => "albedo",
+            Aov::Normal => "normal",
+            Aov::Position => "position",
+            Aov::Depth => "depth",
+            Aov::ObjectId => "object_id",
+            Aov::MaterialId => "material_id",
+        }
+    }
+
+    /// Names of the channels the AOV has in an EXR file.
+    pub fn channels(self) -> &'static [&'static str] {
+        match self {
+            Aov::Albedo => &["R", "G", "B"],
+            Aov::Normal | Aov::Position => &["X", "Y", "Z"],
+            Aov::Depth => &["Z"],
+            Aov::ObjectId | Aov::MaterialId => &["id"],
+        }
+    }
+
+    /// Whether the AOV is an id, which is taken from the first sample of a pixel instead of
+    /// being averaged.
+    pub fn is_id(self) -> bool {
+        matches!(self, Aov::ObjectId | Aov::MaterialId)
+    }
+
+    /// Value of the AOV for the first hit of `r`, everything is 0 where it hits nothing except
+    /// the albedo, which is the background.
+    pub fn value(self, r: &Ray, hit: Option<&HitRecord>, background: Color) -> Color {
+        let Some(rec) = hit else {
+            return match self {
+                Aov::Albedo => background,
+                _ => Color::ZERO,
+            };
+        };
+        match self {
+            Aov::Albedo => rec.material.albedo(rec),
+            Aov::Normal => rec.normal,
+            Aov::Position => rec.p,
+            Aov::Depth => Color::splat(rec.t * r.direction.length()),
+            Aov::ObjectId => Color::splat(rec.object_id as f32),
+            Aov::MaterialId => Color::splat(rec.material_id as f32),
+        }
+    }
+
+    /// Maps the values of an image of the AOV to colors in `[0, 1]` that can be looked at.
+    ///
+    /// Positions and depths are scaled to the range of the image, and every id gets its own
+    /// random color.
+    pub fn visualize(self, pixels: &[Color]) -> Vec<Color> {
+        let min = pixels.iter().fold(Color::INFINITY, |min, &p| min.min(p));
+        let max = pixels
+            .iter()
+            .fold(Color::NEG_INFINITY, |max, &p| max.max(p));
+        pixels
+            .iter()
+            .map(|&p| match self {
+                Aov::Albedo => p.to_array().map(|c| Transfer::Srgb.apply(c)).into(),
+                Aov::Normal => 0.5 * (p + Color::ONE),
+                Aov::Position => (p - min) / (max - min).max(Color::splat(f32::EPSILON)),
+                Aov::Depth => p / max.x.max(f32::EPSILON),
+                Aov::ObjectId | Aov::MaterialId => id_color(p.x as u32),
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Aov {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Aov::ALL.into_iter().find(|aov| aov.name() == s) {
+            Some(aov) => Ok(aov),
+            None => bail!(
+                "unknown AOV `{s}`, expected albedo, normal, position, depth, object_id or \
+                 material_id"
+            ),
+        }
+    }
+}
+
+/// An AOV for every pixel of an image, row by row from the top left.
+#[derive(Debug, Clone)]
+pub struct AovLayer {
+    pub aov: Aov,
+    pub pixels: Vec<Color>,
+}
+
+/// The first hit of `r` that isn't inside a volume, or the last volume hit if the ray doesn't
+/// get through.
+///
+/// Volumes are hit at random points, which would make the AOVs as noisy as the colors.
+pub fn first_surface<'a>(r: &Ray, world: &'a World) -> Option<HitRecord<'a>> {
+    const MAX_VOLUME_HITS: usize = 64;
+
+    let mut hit = world.hit(r)?;
+    for _ in 0..MAX_VOLUME_HITS {
+        if !matches!(hit.material, Material::Isotropic(_)) {
+            break;
+        }
+        match world
+            .objects
+            .hit(r, Interval::new(hit.t + 0.001, f32::INFINITY))
+        {
+            Some(next) => hit = next,
+            None => break,
+        }
+    }
+    Some(hit)
+}
+
+/// A random color for every id but 0, which is black.
+fn id_color(id: u32) -> Color {
+    if id == 0 {
+        return Color::ZERO;
+    }
+    let hash = mix64(id as u64);
+    let channel = |shift: u64| 0.2 + 0.8 * ((hash >> shift) & 0xff) as f32 / 255.0;
+    Color::new(channel(0), channel(8), channel(16))
+}
@@ -74,7 +74,7 @@ impl AABB {
     }
 
     pub fn hit(&self, r: &Ray, mut ray_t: Interval) -> Option<Interval> {
-        for a in 0..2 {
+        for a in 0..3 {
             let inv_d = 1.0 / r.direction[a];
             let orig = r.origin[a];
 
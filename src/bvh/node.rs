@@ -2,8 +2,13 @@ use std::{cmp::Ordering, sync::Arc};
 
 use crate::{
     hittable::{hittable_list::HittableList, HitRecord, Hittable},
-    util::{all::Ray, interval::Interval, random::random_int_in_range},
+    util::{
+        all::Ray,
+        interval::Interval,
+        random::{self, random_int_in_range, with_rng},
+    },
 };
+use rand::Rng;
 
 use super::aabb::AABB;
 
@@ -55,9 +60,18 @@ impl BVHNode {
                 let (left_objects, right_objects) = objects.split_at(mid);
 
                 let (left, right) = if depth < 10 {
+                    // The halves may be built on other threads, give each its own random numbers
+                    // so the tree doesn't depend on how the work was split up.
+                    let (left_seed, right_seed) = with_rng(|rng| (rng.gen(), rng.gen()));
                     rayon::join(
-                        || Arc::new(BVHNode::new(left_objects, depth + 1)),
-                        || Arc::new(BVHNode::new(right_objects, depth + 1)),
+                        || {
+                            random::seed(left_seed);
+                            Arc::new(BVHNode::new(left_objects, depth + 1))
+                        },
+                        || {
+                            random::seed(right_seed);
+                            Arc::new(BVHNode::new(right_objects, depth + 1))
+                        },
                     )
                 } else {
                     (
@@ -79,10 +93,8 @@ impl BVHNode {
 }
 
 impl Hittable for BVHNode {
-    fn hit<'mat>(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
-        if self.bbox.hit(r, ray_t).is_none() {
-            return None;
-        }
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        self.bbox.hit(r, ray_t)?;
 
         match self.left.hit(r, ray_t) {
             Some(left_hit) => Some(
@@ -97,4 +109,16 @@ impl Hittable for BVHNode {
     fn bounding_box(&self) -> &AABB {
         &self.bbox
     }
+
+    fn traversal_cost(&self, r: &Ray, ray_t: Interval) -> u32 {
+        if self.bbox.hit(r, ray_t).is_none() {
+            return 1;
+        }
+
+        let right_t = match self.left.hit(r, ray_t) {
+            Some(left_hit) => Interval::new(ray_t.min, left_hit.t),
+            None => ray_t,
+        };
+        1 + self.left.traversal_cost(r, ray_t) + self.right.traversal_cost(r, right_t)
+    }
 }
@@ -1,19 +1,24 @@
-use std::f32::INFINITY;
-use std::fs::OpenOptions;
-use std::path::Path;
+use std::path::PathBuf;
 use std::sync::mpsc::channel;
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
-use glam::Vec3A;
-use memmap2::MmapMut;
-use rayon::prelude::{IndexedParallelIterator, ParallelIterator};
+use glam::{Vec2, Vec3A};
+use rayon::iter::IndexedParallelIterator;
+use rayon::iter::{ParallelBridge, ParallelIterator};
 use rayon::slice::{ParallelSlice, ParallelSliceMut};
 
-use crate::hittable::Hittable;
-use crate::material::MaterialT;
-use crate::util::all::*;
+use crate::aov::{first_surface, Aov, AovLayer};
+use crate::checkpoint::{Checkpoint, CheckpointHeader, Flusher, PixelStats, Splat};
+use crate::denoise::Denoiser;
+use crate::filter::{FilterKind, SplatLayout};
+use crate::framebuffer::Framebuffer;
+use crate::hittable::{hittable_list::HittableList, Hittable};
+use crate::integrator::{Integrator, IntegratorKind, IntegratorSettings, World};
+use crate::sampler::{sample_unit_disk, Sampler, SamplerKind};
+use crate::tile::{Tile, TileGrid, TileOrder};
+use crate::util::{all::*, random};
 
 #[derive(Debug)]
 pub struct Camera {
@@ -25,8 +30,16 @@ pub struct Camera {
     image_height: i32,
     /// Random samples per pixel.
     pub samples_per_pixel: i32,
-    /// Maximum number of ray bounces into scene.
+    /// Maximum number of ray bounces into scene, for every integrator but
+    /// [`IntegratorKind::Path`], which ends paths by Russian roulette instead.
     pub max_depth: i32,
+    /// Bounces before paths may be ended early by Russian roulette.
+    pub roulette_depth: i32,
+    /// How the light arriving along camera rays is worked out.
+    pub integrator: IntegratorKind,
+    /// How far away occluders count for ambient occlusion, a tenth of the size of the world if
+    /// unset.
+    pub ao_distance: Option<f32>,
     /// Background Color
     pub background: Color,
 
@@ -44,6 +57,32 @@ pub struct Camera {
     /// Distance from camera lookfrom point to plane of perfect focus.
     pub focus_dist: f32,
 
+    /// File to keep the samples of every pixel in, so a render can be resumed or continued.
+    pub checkpoint_path: Option<PathBuf>,
+    /// Render in passes over the whole image instead of finishing one pixel at a time.
+    pub progressive: Option<Progressive>,
+    /// Stop sampling pixels once they have converged, and keep sampling the ones that haven't
+    /// past `samples_per_pixel`, up to [`AdaptiveSampling::max_samples`].
+    pub adaptive: Option<AdaptiveSampling>,
+    /// Width and height of the tiles the image is rendered in.
+    pub tile_size: u32,
+    /// Order the tiles are rendered in.
+    pub tile_order: TileOrder,
+    /// How samples are weighted into the pixels around them.
+    pub filter: FilterKind,
+    /// Radius of the filter in pixels, its default radius if unset.
+    pub filter_radius: Option<f32>,
+    /// Where the random numbers of every sample come from.
+    pub sampler: SamplerKind,
+    /// AOVs to render along with the color, see [`Camera::render_aovs`].
+    pub aovs: Vec<Aov>,
+    /// Denoise the finished image, see [`Denoiser`].
+    pub denoiser: Option<Denoiser>,
+    /// Seed for the random numbers of every sample, the same seed gives the same image.
+    pub seed: u64,
+    /// Identifies the world being rendered, so checkpoints of other scenes are refused.
+    pub scene_hash: u64,
+
     /// Defocus disk horizontal radius.
     defocus_disk_u: Vec3A,
     /// Defocus disk vertical radius.
@@ -83,10 +122,25 @@ impl Default for Camera {
             pixel_delta_v: Default::default(),
             defocus_angle: 0.0,
             focus_dist: 10.0,
+            checkpoint_path: None,
+            progressive: None,
+            adaptive: None,
+            tile_size: 16,
+            tile_order: TileOrder::Scanline,
+            filter: FilterKind::Box,
+            filter_radius: None,
+            sampler: SamplerKind::Independent,
+            aovs: vec![],
+            denoiser: None,
+            seed: crate::SEED,
+            scene_hash: 0,
             defocus_disk_u: Default::default(),
             defocus_disk_v: Default::default(),
             samples_per_pixel: 10,
             max_depth: 10,
+            roulette_depth: 3,
+            integrator: IntegratorKind::Path,
+            ao_distance: None,
             background: Color::ZERO,
         }
     }
@@ -132,149 +186,547 @@ impl Camera {
         self.defocus_disk_v = self.v * defocus_radius;
     }
 
-    /// Get a randomly sampled camera ray for the pixel at location (i, j) originating
-    /// from the camera defocus disk.
-    fn get_ray(&self, i: i32, j: i32) -> Ray {
+    /// Get a camera ray through the point `offset` pixels from the center of the pixel at
+    /// location (i, j), originating from the camera defocus disk.
+    fn get_ray(&self, i: i32, j: i32, offset: Vec2, sampler: &mut dyn Sampler) -> Ray {
         let pixel_center =
             self.pixel00_loc + (i as f32 * self.pixel_delta_u) + (j as f32 * self.pixel_delta_v);
-        let pixel_sample = pixel_center + self.pixel_sample_square();
+        let pixel_sample =
+            pixel_center + (offset.x * self.pixel_delta_u) + (offset.y * self.pixel_delta_v);
 
         let ray_origin = if self.defocus_angle <= 0.0 {
             self.origin
         } else {
-            self.defocus_disk_sample()
+            self.defocus_disk_sample(sampler)
         };
-        let ray_time = rand::random::<f32>();
+        let ray_time = sampler.get_1d();
 
         Ray::new_with_time(ray_origin, pixel_sample - ray_origin, ray_time)
     }
 
     /// Returns a random point in the camera defocus disk.
-    fn defocus_disk_sample(&self) -> Point3 {
-        let p = random_in_unit_disk();
+    fn defocus_disk_sample(&self, sampler: &mut dyn Sampler) -> Point3 {
+        let p = sample_unit_disk(sampler.get_2d());
         self.origin + (p.x * self.defocus_disk_u) + (p.y * self.defocus_disk_v)
     }
 
-    /// Returns a random point in the square surrounding a pixel at the origin.
-    fn pixel_sample_square(&self) -> Vec3A {
-        let px = -0.5 + rand::random::<f32>();
-        let py = -0.5 + rand::random::<f32>();
-        (px * self.pixel_delta_u) + (py * self.pixel_delta_v)
+    /// Returns a random offset from the center of a pixel to a point in it.
+    fn pixel_sample_offset(&self, sampler: &mut dyn Sampler) -> Vec2 {
+        sampler.get_2d() - 0.5
     }
 
-    fn ray_color(&self, r: &Ray, world: &dyn Hittable, depth: i32) -> Color {
-        if depth <= 0 {
-            return Color::ZERO;
-        }
-
-        if let Some(rec) = world.hit(r, Interval::new(0.001, INFINITY)) {
-            let color_from_emmision = rec.material.emitted(rec.u, rec.v, rec.p);
-
-            let color_from_scatter =
-                if let Some((scattered, attenunation)) = rec.material.scatter(r, &rec) {
-                    attenunation * self.ray_color(&scattered, world, depth - 1)
-                } else {
-                    return color_from_emmision;
-                };
-
-            return color_from_emmision + color_from_scatter;
-        }
-
-        self.background
+    /// Renders the world into an image of linear colors.
+    ///
+    /// If `checkpoint_path` is set, the samples of every pixel are also kept in that file as they
+    /// are rendered. Pixels already in it from an earlier render of the same scene only get the
+    /// samples they are missing, so an interrupted render resumes where it stopped and raising
+    /// `samples_per_pixel` adds to what was already rendered, except with the stratified sampler,
+    /// whose strata depend on it.
+    pub fn render(&mut self, world: &dyn Hittable, lights: &HittableList) -> Result<Framebuffer> {
+        self.render_progressive(world, lights, |_, _| Ok(()))
     }
 
-    pub fn render(&mut self, world: &dyn Hittable) -> Result<()> {
+    /// Like [`Camera::render`], but calls `on_pass` with the image so far after every pass.
+    ///
+    /// Without `progressive` settings the whole image is rendered in a single pass.
+    pub fn render_progressive(
+        &mut self,
+        world: &dyn Hittable,
+        lights: &HittableList,
+        mut on_pass: impl FnMut(&PassReport, &Framebuffer) -> Result<()>,
+    ) -> Result<Framebuffer> {
         self.initialize();
 
-        let total_pixels = self.image_height * self.image_width;
-        let pixels_per_percent = total_pixels / 100;
-        let file_size = (total_pixels * 3) as u64;
+        let world = World {
+            objects: world,
+            lights,
+            background: self.background,
+        };
+        let integrator = self
+            .integrator
+            .create(&self.integrator_settings(world.objects));
+
+        let total_pixels = (self.image_height * self.image_width) as usize;
+
+        let mut framebuffer = Framebuffer::new(self.image_width as u32, self.image_height as u32);
+        let mut checkpoint = self
+            .checkpoint_path
+            .as_deref()
+            .map(|path| Checkpoint::open(path, &self.checkpoint_header()))
+            .transpose()?;
+        let flusher = checkpoint.as_ref().map(Checkpoint::flusher);
+        let (mut scratch_records, mut scratch_splats) = (vec![], vec![]);
+        let (records, splats) = match &mut checkpoint {
+            Some(checkpoint) => checkpoint.data_mut(),
+            None => {
+                scratch_records.resize(total_pixels * PixelStats::BYTES, 0);
+                scratch_splats.resize(self.splat_layout().bytes(), 0);
+                (&mut scratch_records[..], &mut scratch_splats[..])
+            }
+        };
+        let mut data = RenderData {
+            records,
+            splats,
+            flusher,
+        };
 
-        let mut indices = (0..self.image_height)
-            .flat_map(|j| (0..self.image_width).map(move |i| (false, (i, j))))
-            .collect::<Vec<_>>();
+        let samples_per_pixel = self.samples_per_pixel.max(0) as u32;
+        let max_samples = self
+            .adaptive
+            .as_ref()
+            .map_or(samples_per_pixel, |adaptive| {
+                adaptive.max_samples.max(samples_per_pixel)
+            });
+        let progressive = self.progressive.clone().unwrap_or(Progressive {
+            samples_per_pass: max_samples,
+            ..Default::default()
+        });
+
+        let now = Instant::now();
+        let deadline = progressive.time_budget.map(|budget| now + budget);
+
+        let mut pass = 0;
+        let mut target = 0;
+        loop {
+            pass += 1;
+            target = (target + progressive.samples_per_pass.max(1)).min(max_samples);
+
+            let samples_taken = self.render_pass(
+                &world,
+                &*integrator,
+                &mut data,
+                PassGoal { target, deadline },
+                self.progressive.is_none(),
+            )?;
+            self.splat_layout()
+                .resolve(data.splats, &mut framebuffer.pixels);
+
+            let (noise, average_samples) = summarize(data.records);
+            let report = PassReport {
+                pass,
+                samples_per_pixel: target,
+                average_samples,
+                noise,
+                elapsed: now.elapsed(),
+            };
+            if self.progressive.is_some() || self.adaptive.is_some() {
+                println!(
+                    "Pass {}: {} spp ({:.1} on average), noise {:.4}, {:.1}s",
+                    report.pass,
+                    report.samples_per_pixel,
+                    report.average_samples,
+                    report.noise,
+                    report.elapsed.as_secs_f32()
+                );
+            }
+            on_pass(&report, &framebuffer)?;
+
+            let out_of_time = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            let converged = progressive
+                .noise_threshold
+                .is_some_and(|threshold| report.noise <= threshold);
+            // With adaptive sampling every pixel may have converged before reaching the target.
+            let settled = samples_taken == 0 && self.adaptive.is_some();
+            if target >= max_samples || out_of_time || converged || settled {
+                break;
+            }
+        }
 
-        let mut file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open("_image.raw")?;
-
-        if Path::new("_image.raw").exists() {
-            let contents = std::fs::read("_image.raw")?;
-            if contents.len() == file_size as usize {
-                indices = contents
-                    .par_chunks(3)
-                    .zip(indices)
-                    .map(|(c, (_, p))| (c != &[0, 0, 0], p))
-                    .collect();
-            } else {
-                std::fs::remove_file("_image.raw")?;
-                file = OpenOptions::new()
-                    .read(true)
-                    .write(true)
-                    .create(true)
-                    .open("_image.raw")?;
+        match &self.denoiser {
+            Some(denoiser) => {
+                let mut aovs = self.aovs.clone();
+                aovs.extend(
+                    Denoiser::GUIDES
+                        .iter()
+                        .filter(|aov| !self.aovs.contains(aov)),
+                );
+                framebuffer.aovs = self.render_aovs(&world, &aovs);
+                denoiser.apply(&mut framebuffer);
+                framebuffer.aovs.truncate(self.aovs.len());
             }
+            None => framebuffer.aovs = self.render_aovs(&world, &self.aovs),
         }
 
-        file.set_len(file_size)?;
-        let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+        println!("Rendered in {} seconds!", now.elapsed().as_secs_f32());
 
-        let pixels = mmap.par_chunks_mut(3).zip(indices);
+        if let Some(checkpoint) = checkpoint {
+            checkpoint.flush()?;
+        }
 
-        let now = Instant::now();
-        let (sender, recv) = channel::<()>();
+        Ok(framebuffer)
+    }
 
-        thread::spawn(move || {
+    /// Brings every pixel up to the goal's sample count, unless its deadline passes first or the
+    /// pixel has converged. Returns the number of samples taken.
+    ///
+    /// The image is rendered a tile at a time, and a tile's records and splats are only written
+    /// once all of its pixels are done. They are then flushed to the checkpoint, if there is one.
+    fn render_pass(
+        &self,
+        world: &World,
+        integrator: &dyn Integrator,
+        data: &mut RenderData,
+        goal: PassGoal,
+        report_progress: bool,
+    ) -> Result<u64> {
+        let grid = self.tile_grid();
+        let total_pixels = grid.width * grid.height;
+
+        let (sender, recv) = channel::<u32>();
+        let progress = thread::spawn(move || {
             let mut pixels_done = 0;
-            loop {
-                if let Ok(_) = recv.recv() {
-                    pixels_done += 1;
-                    if pixels_done % pixels_per_percent == 0 {
-                        println!(
-                            "{}%",
-                            (pixels_done as f32 / total_pixels as f32 * 100.0) as u8
-                        );
-                    }
-                } else {
-                    println!("Rendered in {} seconds!", now.elapsed().as_secs_f32());
-                    println!("Writing {total_pixels} pixels...");
-                    break;
+            let mut percent_done = 0;
+            while let Ok(pixels) = recv.recv() {
+                pixels_done += pixels;
+                let percent = (pixels_done as u64 * 100 / total_pixels as u64) as u32;
+                if report_progress && percent > percent_done {
+                    percent_done = percent;
+                    println!("{percent}%");
                 }
             }
         });
 
-        pixels.for_each(|(out, (done, (i, j)))| {
-            if done {
-                sender.send(()).unwrap();
-                return;
+        let flusher = data.flusher.as_ref();
+        let mut record_tiles = grid.split(data.records, PixelStats::BYTES);
+        let mut splat_blocks = data
+            .splats
+            .chunks_mut(self.splat_layout().block_bytes())
+            .collect::<Vec<_>>();
+        let work = grid
+            .order(self.tile_order)
+            .into_iter()
+            .map(|index| {
+                let columns = grid.columns() as usize;
+                let tile = grid.tile((index % columns) as u32, (index / columns) as u32);
+                let records = std::mem::take(&mut record_tiles[index]);
+                let splats = std::mem::take(&mut splat_blocks[index]);
+                (tile, records, splats)
+            })
+            .collect::<Vec<_>>();
+
+        // Bridging hands the tiles out in order, instead of rayon splitting them up by position.
+        let samples_taken = work
+            .into_iter()
+            .par_bridge()
+            .map(|(tile, mut records, splats)| {
+                let samples = self.render_tile(world, integrator, tile, &mut records, splats, goal);
+                if let Some(flusher) = flusher.filter(|_| samples > 0) {
+                    for row in &records {
+                        flusher.flush(row)?;
+                    }
+                    flusher.flush(splats)?;
+                }
+                sender.send(tile.pixels()).unwrap();
+                Ok(samples)
+            })
+            .try_reduce(|| 0, |a, b| Ok(a + b));
+
+        drop(sender);
+        progress.join().unwrap();
+
+        samples_taken
+    }
+
+    /// Renders a tile given the rows of its pixel records and its splat block.
+    fn render_tile(
+        &self,
+        world: &World,
+        integrator: &dyn Integrator,
+        tile: Tile,
+        records: &mut [&mut [u8]],
+        splat_block: &mut [u8],
+        goal: PassGoal,
+    ) -> u64 {
+        if goal
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return 0;
+        }
+
+        let samples_per_pixel = self.samples_per_pixel.max(1) as u32;
+        let mut sampler = self.sampler.create(self.seed, samples_per_pixel);
+        let layout = self.splat_layout();
+        let filter = self.filter.create(self.filter_radius());
+        let mut splats = splat_block
+            .chunks(Splat::BYTES)
+            .map(Splat::read)
+            .collect::<Vec<_>>();
+        let mut pixels = records
+            .iter()
+            .flat_map(|row| row.chunks(PixelStats::BYTES).map(PixelStats::read))
+            .collect::<Vec<_>>();
+
+        let mut samples_taken = 0;
+        for (index, stats) in pixels.iter_mut().enumerate() {
+            let (x, y) = (index as u32 % tile.width, index as u32 / tile.width);
+            let (i, j) = ((tile.x + x) as i32, (tile.y + y) as i32);
+            let pixel = j as u64 * self.image_width as u64 + i as u64;
+
+            let before = stats.samples;
+            while stats.samples < goal.target
+                && !self.adaptive.as_ref().is_some_and(|a| a.converged(stats))
+            {
+                // Every sample has its own random numbers, so the result doesn't depend on
+                // which thread renders it or whether the render was resumed.
+                random::seed(random::sample_seed(self.seed, pixel, stats.samples as u64));
+                sampler.start_sample(pixel, stats.samples);
+                let offset = self.pixel_sample_offset(&mut *sampler);
+                let r = self.get_ray(i, j, offset, &mut *sampler);
+                let color = integrator.ray_color(&r, world, &mut *sampler);
+                stats.add_sample(color);
+                layout.splat(&*filter, &mut splats, x, y, offset, color);
             }
 
-            let mut pixel_color = Color::ZERO;
-            for _ in 0..self.samples_per_pixel {
-                let r = self.get_ray(i, j);
-                pixel_color += self.ray_color(&r, world, self.max_depth);
+            samples_taken += (stats.samples - before) as u64;
+        }
+
+        // A pixel only counts as rendered once its samples are in the splats too, so an
+        // interrupted render never resumes with pixels that are missing from the image.
+        if samples_taken > 0 {
+            let records = records
+                .iter_mut()
+                .flat_map(|row| row.chunks_mut(PixelStats::BYTES));
+            for (stats, record) in pixels.iter().zip(records) {
+                stats.write(record);
+            }
+            for (splat, record) in splats.iter().zip(splat_block.chunks_mut(Splat::BYTES)) {
+                splat.write(record);
             }
+        }
+        samples_taken
+    }
 
-            out.copy_from_slice(&convert_color(pixel_color, self.samples_per_pixel));
-            sender.send(()).unwrap();
-        });
-        drop(sender);
+    /// Renders the `aovs` from the first surfaces hit by the first [`AOV_SAMPLES`] camera rays
+    /// of every pixel, which are the same rays the color starts with.
+    ///
+    /// Ids come from the first sample of a pixel, the other AOVs are averaged.
+    fn render_aovs(&self, world: &World, aovs: &[Aov]) -> Vec<AovLayer> {
+        if aovs.is_empty() {
+            return vec![];
+        }
 
-        image::save_buffer(
-            "image.jpg",
-            &mmap,
+        let width = self.image_width as usize;
+        let count = aovs.len();
+        let samples = self.samples_per_pixel.clamp(1, AOV_SAMPLES as i32) as u32;
+
+        // The AOVs of a pixel are next to each other, and split into layers at the end.
+        let mut values = vec![Color::ZERO; width * self.image_height as usize * count];
+        values
+            .par_chunks_mut(width * count)
+            .enumerate()
+            .for_each(|(j, row)| {
+                let mut sampler = self
+                    .sampler
+                    .create(self.seed, self.samples_per_pixel.max(1) as u32);
+                for (i, pixel_values) in row.chunks_mut(count).enumerate() {
+                    let pixel = (j * width + i) as u64;
+                    for sample in 0..samples {
+                        random::seed(random::sample_seed(self.seed, pixel, sample as u64));
+                        sampler.start_sample(pixel, sample);
+                        let offset = self.pixel_sample_offset(&mut *sampler);
+                        let r = self.get_ray(i as i32, j as i32, offset, &mut *sampler);
+                        let hit = first_surface(&r, world);
+                        for (aov, value) in aovs.iter().zip(pixel_values.iter_mut()) {
+                            if aov.is_id() && sample > 0 {
+                                continue;
+                            }
+                            let weight = if aov.is_id() {
+                                1.0
+                            } else {
+                                1.0 / samples as f32
+                            };
+                            *value += weight * aov.value(&r, hit.as_ref(), world.background);
+                        }
+                    }
+                }
+            });
+
+        aovs.iter()
+            .enumerate()
+            .map(|(k, &aov)| AovLayer {
+                aov,
+                pixels: values.iter().skip(k).step_by(count).copied().collect(),
+            })
+            .collect()
+    }
+
+    fn tile_grid(&self) -> TileGrid {
+        TileGrid::new(
             self.image_width as u32,
             self.image_height as u32,
-            image::ColorType::Rgb8,
-        )?;
+            self.tile_size,
+        )
+    }
+
+    fn filter_radius(&self) -> f32 {
+        self.filter_radius
+            .unwrap_or_else(|| self.filter.default_radius())
+    }
+
+    fn splat_layout(&self) -> SplatLayout {
+        SplatLayout::new(self.tile_grid(), self.filter_radius())
+    }
+
+    fn integrator_settings(&self, world: &dyn Hittable) -> IntegratorSettings {
+        let ao_distance = self.ao_distance.unwrap_or_else(|| {
+            let bbox = world.bounding_box();
+            let size = Vec3A::new(bbox.x.size(), bbox.y.size(), bbox.z.size());
+            0.1 * size.length()
+        });
+        IntegratorSettings {
+            max_depth: self.max_depth,
+            roulette_depth: self.roulette_depth,
+            ao_distance,
+        }
+    }
+
+    /// What a checkpoint for this camera has to match to be resumed.
+    fn checkpoint_header(&self) -> CheckpointHeader {
+        CheckpointHeader {
+            scene_hash: self.scene_hash,
+            seed: self.seed,
+            width: self.image_width as u32,
+            height: self.image_height as u32,
+            max_depth: self.max_depth as u32,
+            roulette_depth: self.roulette_depth as u32,
+            integrator: self.integrator as u32,
+            sampler: self.sampler as u32,
+            strata: match self.sampler {
+                SamplerKind::Stratified => self.samples_per_pixel.max(1) as u32,
+                _ => 0,
+            },
+            tile_size: self.tile_size,
+            filter: self.filter as u32,
+            filter_radius: self.filter_radius(),
+            background: self.background,
+            vfov: self.vfov,
+            lookfrom: self.lookfrom,
+            lookat: self.lookat,
+            vup: self.vup,
+            defocus_angle: self.defocus_angle,
+            focus_dist: self.focus_dist,
+        }
+    }
+}
+
+/// When and how often a progressive render stops to report the image so far.
+#[derive(Debug, Default, Clone)]
+pub struct Progressive {
+    /// Samples added to every pixel in each pass.
+    pub samples_per_pass: u32,
+    /// Stop after the pass that runs past this much time.
+    pub time_budget: Option<Duration>,
+    /// Stop once the estimated noise, see [`PassReport::noise`], is at or below this.
+    pub noise_threshold: Option<f32>,
+}
+
+/// Most camera rays per pixel the AOVs are rendered from.
+pub const AOV_SAMPLES: u32 = 16;
+
+/// Where the samples of a render are kept from pass to pass.
+struct RenderData<'a> {
+    /// The [`PixelStats`] of every pixel.
+    records: &'a mut [u8],
+    /// The [`Splat`] blocks of the tiles, see [`SplatLayout`].
+    splats: &'a mut [u8],
+    /// Writes finished tiles to the checkpoint, if there is one.
+    flusher: Option<Flusher>,
+}
 
-        drop(mmap);
-        std::fs::remove_file("_image.raw")?;
+/// What a pass should bring every pixel to.
+#[derive(Debug, Clone, Copy)]
+struct PassGoal {
+    /// Samples per pixel.
+    target: u32,
+    /// When to stop starting new tiles.
+    deadline: Option<Instant>,
+}
 
-        println!("Completed in {}s.", now.elapsed().as_secs_f32());
+/// When a pixel has had enough samples.
+#[derive(Debug, Clone)]
+pub struct AdaptiveSampling {
+    /// Samples every pixel gets before it can be considered converged.
+    pub min_samples: u32,
+    /// Relative standard error of a pixel's luminance at which it has converged.
+    pub tolerance: f32,
+    /// Samples a pixel that hasn't converged gets at most, even beyond the camera's
+    /// `samples_per_pixel`.
+    pub max_samples: u32,
+}
 
-        Ok(())
+impl AdaptiveSampling {
+    pub fn converged(&self, stats: &PixelStats) -> bool {
+        stats.samples >= self.min_samples.max(2) && stats.relative_error() <= self.tolerance
+    }
+}
+
+/// The state of a render after a pass.
+#[derive(Debug, Clone)]
+pub struct PassReport {
+    /// 1-based number of the pass.
+    pub pass: usize,
+    /// Samples every pixel has, unless the time budget ran out during the pass or adaptive
+    /// sampling found the pixel converged earlier.
+    pub samples_per_pixel: u32,
+    /// Samples per pixel, averaged over the image.
+    pub average_samples: f32,
+    /// Average relative standard error of the pixels.
+    pub noise: f32,
+    /// Time since the render started.
+    pub elapsed: Duration,
+}
+
+/// Average relative standard error and sample count over every pixel.
+fn summarize(records: &[u8]) -> (f32, f32) {
+    let pixels = records
+        .par_chunks(PixelStats::BYTES)
+        .map(|record| {
+            let stats = PixelStats::read(record);
+            (stats.relative_error(), stats.samples as f32)
+        })
+        .collect::<Vec<_>>();
+    let (error, samples) = pixels.iter().fold((0.0, 0.0), |(e, n), &(error, samples)| {
+        (e + error, n + samples)
+    });
+    (error / pixels.len() as f32, samples / pixels.len() as f32)
+}
+
+/// Optional replacements for the settings a scene picks for its camera.
+#[derive(Debug, Default, Clone)]
+pub struct CameraOverrides {
+    pub image_width: Option<i32>,
+    pub samples_per_pixel: Option<i32>,
+    pub max_depth: Option<i32>,
+    pub vfov: Option<f32>,
+    pub lookfrom: Option<Point3>,
+    pub lookat: Option<Point3>,
+    pub defocus_angle: Option<f32>,
+}
+
+impl CameraOverrides {
+    /// Replaces every camera setting that has an override.
+    pub fn apply(&self, camera: &mut Camera) {
+        if let Some(image_width) = self.image_width {
+            camera.image_width = image_width;
+        }
+        if let Some(samples_per_pixel) = self.samples_per_pixel {
+            camera.samples_per_pixel = samples_per_pixel;
+        }
+        if let Some(max_depth) = self.max_depth {
+            camera.max_depth = max_depth;
+        }
+        if let Some(vfov) = self.vfov {
+            camera.vfov = vfov;
+        }
+        if let Some(lookfrom) = self.lookfrom {
+            camera.lookfrom = lookfrom;
+        }
+        if let Some(lookat) = self.lookat {
+            camera.lookat = lookat;
+        }
+        if let Some(defocus_angle) = self.defocus_angle {
+            camera.defocus_angle = defocus_angle;
+        }
     }
 }
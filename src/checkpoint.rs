@@ -0,0 +1,410 @@
+//! Checkpoint files that let a render be interrupted and resumed, or continued with more samples.
+//!
+//! A checkpoint starts with [`MAGIC`], a format version and a [`CheckpointHeader`], followed by
+//! one [`PixelStats`] record per pixel, row by row from the top left, and then the [`Splat`]
+//! blocks of every tile laid out as in [`SplatLayout`]. Everything is little endian.
+
+use std::{fs::OpenOptions, path::Path, sync::Arc};
+
+use anyhow::{bail, Context, Result};
+use memmap2::MmapRaw;
+
+use crate::{
+    filter::SplatLayout,
+    tile::TileGrid,
+    util::{
+        color::{luminance, Color},
+        Point3,
+    },
+};
+
+const MAGIC: &[u8; 8] = b"RTCHKPT\0";
+const VERSION: u32 = 6;
+
+/// Everything a checkpoint must match to be resumed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckpointHeader {
+    /// Identifies the world, see [`scene_hash`].
+    pub scene_hash: u64,
+    /// Samples from different seeds are different random streams, which mustn't be mixed.
+    pub seed: u64,
+    pub width: u32,
+    pub height: u32,
+    pub max_depth: u32,
+    pub roulette_depth: u32,
+    /// The [`IntegratorKind`](crate::integrator::IntegratorKind) as a number.
+    pub integrator: u32,
+    /// The [`SamplerKind`](crate::sampler::SamplerKind) as a number.
+    pub sampler: u32,
+    /// Strata of the stratified sampler, which are laid out for `samples_per_pixel` samples, 0
+    /// for the other samplers that can keep adding samples.
+    pub strata: u32,
+    /// Samples are splatted per tile, so the tiles have to stay the same.
+    pub tile_size: u32,
+    /// The [`FilterKind`](crate::filter::FilterKind) as a number.
+    pub filter: u32,
+    pub filter_radius: f32,
+    pub background: Color,
+    pub vfov: f32,
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vup: Point3,
+    pub defocus_angle: f32,
+    pub focus_dist: f32,
+}
+
+impl CheckpointHeader {
+    const BYTES: usize = 2 * 8 + 9 * 4 + 16 * 4;
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.scene_hash.to_le_bytes());
+        out.extend_from_slice(&self.seed.to_le_bytes());
+        for n in [
+            self.width,
+            self.height,
+            self.max_depth,
+            self.roulette_depth,
+            self.integrator,
+            self.sampler,
+            self.strata,
+            self.tile_size,
+            self.filter,
+        ] {
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        let floats = [
+            &[self.filter_radius],
+            self.background.to_array().as_slice(),
+            &[self.vfov],
+            &self.lookfrom.to_array(),
+            &self.lookat.to_array(),
+            &self.vup.to_array(),
+            &[self.defocus_angle, self.focus_dist],
+        ]
+        .concat();
+        for f in floats {
+            out.extend_from_slice(&f.to_le_bytes());
+        }
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        let mut reader = Reader(bytes);
+        Self {
+            scene_hash: u64::from_le_bytes(reader.take()),
+            seed: u64::from_le_bytes(reader.take()),
+            width: u32::from_le_bytes(reader.take()),
+            height: u32::from_le_bytes(reader.take()),
+            max_depth: u32::from_le_bytes(reader.take()),
+            roulette_depth: u32::from_le_bytes(reader.take()),
+            integrator: u32::from_le_bytes(reader.take()),
+            sampler: u32::from_le_bytes(reader.take()),
+            strata: u32::from_le_bytes(reader.take()),
+            tile_size: u32::from_le_bytes(reader.take()),
+            filter: u32::from_le_bytes(reader.take()),
+            filter_radius: reader.f32(),
+            background: reader.vec3(),
+            vfov: reader.f32(),
+            lookfrom: reader.vec3(),
+            lookat: reader.vec3(),
+            vup: reader.vec3(),
+            defocus_angle: reader.f32(),
+            focus_dist: reader.f32(),
+        }
+    }
+
+    /// Where the splats of the tiles are kept.
+    pub fn splat_layout(&self) -> SplatLayout {
+        let grid = TileGrid::new(self.width, self.height, self.tile_size);
+        SplatLayout::new(grid, self.filter_radius)
+    }
+
+    /// Names of the fields that differ from `other`.
+    fn mismatches(&self, other: &Self) -> Vec<&'static str> {
+        let mut fields = vec![];
+        let mut check = |same: bool, name| {
+            if !same {
+                fields.push(name);
+            }
+        };
+        check(self.scene_hash == other.scene_hash, "scene");
+        check(self.seed == other.seed, "seed");
+        check(
+            self.width == other.width && self.height == other.height,
+            "resolution",
+        );
+        check(self.max_depth == other.max_depth, "max_depth");
+        check(
+            self.roulette_depth == other.roulette_depth,
+            "roulette_depth",
+        );
+        check(self.integrator == other.integrator, "integrator");
+        check(self.sampler == other.sampler, "sampler");
+        check(self.strata == other.strata, "samples_per_pixel");
+        check(self.tile_size == other.tile_size, "tile_size");
+        check(
+            self.filter == other.filter && self.filter_radius == other.filter_radius,
+            "filter",
+        );
+        check(self.background == other.background, "background");
+        check(self.vfov == other.vfov, "vfov");
+        check(self.lookfrom == other.lookfrom, "lookfrom");
+        check(self.lookat == other.lookat, "lookat");
+        check(self.vup == other.vup, "vup");
+        check(self.defocus_angle == other.defocus_angle, "defocus_angle");
+        check(self.focus_dist == other.focus_dist, "focus_dist");
+        fields
+    }
+}
+
+/// Reads fixed size fields off the front of a byte slice.
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let (head, rest) = self.0.split_at(N);
+        self.0 = rest;
+        head.try_into().unwrap()
+    }
+
+    fn f32(&mut self) -> f32 {
+        f32::from_le_bytes(self.take())
+    }
+
+    fn vec3(&mut self) -> Color {
+        Color::new(self.f32(), self.f32(), self.f32())
+    }
+}
+
+/// A memory mapped checkpoint file.
+pub struct Checkpoint {
+    mmap: Arc<MmapRaw>,
+    /// Bytes of the pixel records.
+    records_size: usize,
+}
+
+impl Checkpoint {
+    const DATA_OFFSET: usize = MAGIC.len() + 4 + CheckpointHeader::BYTES;
+
+    /// Opens the checkpoint at `path`, or creates an empty one if there is none.
+    ///
+    /// Fails if an existing checkpoint was written for a different scene or camera.
+    pub fn open(path: &Path, header: &CheckpointHeader) -> Result<Self> {
+        let pixels = header.width as usize * header.height as usize;
+        let records_size = pixels * PixelStats::BYTES;
+        let file_size = (Self::DATA_OFFSET + records_size + header.splat_layout().bytes()) as u64;
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("Failed to open checkpoint {}", path.display()))?;
+        let existing_size = file.metadata()?.len();
+
+        if existing_size == 0 {
+            file.set_len(file_size)?;
+            let mut checkpoint = Self {
+                mmap: Arc::new(MmapRaw::map_raw(&file)?),
+                records_size,
+            };
+
+            let mut prefix = Vec::with_capacity(Self::DATA_OFFSET);
+            prefix.extend_from_slice(MAGIC);
+            prefix.extend_from_slice(&VERSION.to_le_bytes());
+            header.write(&mut prefix);
+            checkpoint.bytes_mut()[..Self::DATA_OFFSET].copy_from_slice(&prefix);
+
+            return Ok(checkpoint);
+        }
+
+        let mut checkpoint = Self {
+            mmap: Arc::new(MmapRaw::map_raw(&file)?),
+            records_size,
+        };
+        let mmap = checkpoint.bytes_mut();
+        if mmap.len() < MAGIC.len() + 4 || &mmap[..MAGIC.len()] != MAGIC {
+            bail!("{} is not a checkpoint file", path.display());
+        }
+
+        let version = u32::from_le_bytes(mmap[MAGIC.len()..MAGIC.len() + 4].try_into().unwrap());
+        if version != VERSION {
+            bail!(
+                "Checkpoint {} has version {version}, but only version {VERSION} is supported",
+                path.display()
+            );
+        }
+
+        if mmap.len() < Self::DATA_OFFSET {
+            bail!("Checkpoint {} is truncated", path.display());
+        }
+
+        let stored = CheckpointHeader::read(&mmap[MAGIC.len() + 4..Self::DATA_OFFSET]);
+        let mismatches = stored.mismatches(header);
+        if !mismatches.is_empty() {
+            bail!(
+                "Checkpoint {} was made with a different {}, delete it to start over",
+                path.display(),
+                mismatches.join(", ")
+            );
+        }
+
+        if existing_size != file_size {
+            bail!("Checkpoint {} is truncated", path.display());
+        }
+
+        Ok(checkpoint)
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        // SAFETY: The map is only read or written through the slices handed out here, which
+        // borrow the checkpoint mutably, and the file is never truncated while it is mapped.
+        unsafe { std::slice::from_raw_parts_mut(self.mmap.as_mut_ptr(), self.mmap.len()) }
+    }
+
+    /// The pixel records, [`PixelStats::BYTES`] each, and the splat blocks of the tiles.
+    pub fn data_mut(&mut self) -> (&mut [u8], &mut [u8]) {
+        let records_size = self.records_size;
+        self.bytes_mut()[Self::DATA_OFFSET..].split_at_mut(records_size)
+    }
+
+    /// Writes parts of the data to disk while [`Checkpoint::data_mut`] is borrowed.
+    pub fn flusher(&self) -> Flusher {
+        Flusher(self.mmap.clone())
+    }
+
+    pub fn flush(&self) -> Result<()> {
+        Ok(self.mmap.flush()?)
+    }
+}
+
+/// Writes the parts of a checkpoint that are done to disk, from any thread.
+pub struct Flusher(Arc<MmapRaw>);
+
+impl Flusher {
+    /// Starts writing `bytes`, a part of [`Checkpoint::data_mut`], to disk without waiting for
+    /// it to finish.
+    pub fn flush(&self, bytes: &[u8]) -> Result<()> {
+        let offset = (bytes.as_ptr() as usize)
+            .checked_sub(self.0.as_ptr() as usize)
+            .filter(|offset| offset + bytes.len() <= self.0.len())
+            .context("the bytes are not part of the checkpoint")?;
+        Ok(self.0.flush_async_range(offset, bytes.len())?)
+    }
+}
+
+/// The samples taken for a pixel so far.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PixelStats {
+    pub samples: u32,
+    /// Sum of the sampled colors.
+    pub sum: Color,
+    /// Sum of the squared luminance of the sampled colors, for estimating variance.
+    pub luminance_sq_sum: f32,
+}
+
+impl PixelStats {
+    /// Bytes used by a record, a `u32` sample count and four `f32` sums.
+    pub const BYTES: usize = 20;
+
+    /// Pixels darker than this are treated as this bright when estimating relative error, so
+    /// black pixels don't need an endless number of samples.
+    const MIN_LUMINANCE: f32 = 0.01;
+
+    pub fn read(record: &[u8]) -> Self {
+        let mut reader = Reader(record);
+        Self {
+            samples: u32::from_le_bytes(reader.take()),
+            sum: reader.vec3(),
+            luminance_sq_sum: reader.f32(),
+        }
+    }
+
+    pub fn write(&self, record: &mut [u8]) {
+        record[..4].copy_from_slice(&self.samples.to_le_bytes());
+        let floats = self
+            .sum
+            .to_array()
+            .into_iter()
+            .chain([self.luminance_sq_sum]);
+        for (i, f) in floats.enumerate() {
+            record[4 + i * 4..8 + i * 4].copy_from_slice(&f.to_le_bytes());
+        }
+    }
+
+    pub fn add_sample(&mut self, color: Color) {
+        self.samples += 1;
+        self.sum += color;
+        self.luminance_sq_sum += luminance(color).powi(2);
+    }
+
+    pub fn mean(&self) -> Color {
+        if self.samples == 0 {
+            Color::ZERO
+        } else {
+            self.sum / self.samples as f32
+        }
+    }
+
+    /// Sample variance of the luminance.
+    pub fn luminance_variance(&self) -> f32 {
+        if self.samples < 2 {
+            return f32::INFINITY;
+        }
+        let n = self.samples as f32;
+        let mean = luminance(self.sum) / n;
+        ((self.luminance_sq_sum / n - mean * mean) * n / (n - 1.0)).max(0.0)
+    }
+
+    /// Standard error of the mean luminance relative to the mean itself.
+    pub fn relative_error(&self) -> f32 {
+        let standard_error = (self.luminance_variance() / self.samples as f32).sqrt();
+        standard_error / luminance(self.mean()).max(Self::MIN_LUMINANCE)
+    }
+}
+
+/// Weighted sum of the samples splatted to a pixel by one tile.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Splat {
+    pub sum: Color,
+    pub weight: f32,
+}
+
+impl Splat {
+    /// Bytes used by a record, four `f32`s.
+    pub const BYTES: usize = 16;
+
+    pub fn read(record: &[u8]) -> Self {
+        let mut reader = Reader(record);
+        Self {
+            sum: reader.vec3(),
+            weight: reader.f32(),
+        }
+    }
+
+    pub fn write(&self, record: &mut [u8]) {
+        let floats = self.sum.to_array().into_iter().chain([self.weight]);
+        for (i, f) in floats.enumerate() {
+            record[i * 4..i * 4 + 4].copy_from_slice(&f.to_le_bytes());
+        }
+    }
+
+    /// The weighted average of the samples, kept from going negative by filters with negative
+    /// lobes.
+    pub fn color(&self) -> Color {
+        if self.weight > 0.0 {
+            (self.sum / self.weight).max(Color::ZERO)
+        } else {
+            Color::ZERO
+        }
+    }
+}
+
+/// A 64-bit FNV-1a hash of a scene description, stable across builds and platforms.
+pub fn scene_hash(description: &str) -> u64 {
+    description
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+}
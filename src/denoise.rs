@@ -0,0 +1,202 @@
+//! Denoising of finished renders with the edge-avoiding à-trous wavelet transform, after
+//! Dammertz et al., "Edge-Avoiding À-Trous Wavelet Transform for fast Global Illumination
+//! Filtering".
+//!
+//! Every iteration blurs the image with a 5x5 B3 spline kernel whose taps are twice as far apart
+//! as in the iteration before, so a few iterations reach far for little work. Taps are weighted
+//! down where the color, albedo, normal or depth differ from the pixel's, which keeps edges sharp.
+
+use rayon::{iter::IndexedParallelIterator, iter::ParallelIterator, slice::ParallelSliceMut};
+
+use crate::{
+    aov::Aov,
+    framebuffer::Framebuffer,
+    util::color::{luminance, Color},
+};
+
+/// Settings of the denoiser, the sigmas are how different pixels may be and still be blurred
+/// together.
+#[derive(Debug, Clone)]
+pub struct Denoiser {
+    /// Filter iterations, the last one reaches `2 << iterations` pixels away.
+    pub iterations: u32,
+    /// Of tone mapped colors, halved every iteration as the image gets smoother.
+    pub color_sigma: f32,
+    pub normal_sigma: f32,
+    pub albedo_sigma: f32,
+    /// Of depths relative to the pixel's, per pixel between them.
+    pub depth_sigma: f32,
+    /// How many times brighter than all of its neighbors a pixel must be to be taken for a
+    /// firefly and darkened before filtering, 0 to keep every pixel.
+    pub firefly_ratio: f32,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            iterations: 5,
+            color_sigma: 0.5,
+            normal_sigma: 0.3,
+            albedo_sigma: 0.1,
+            depth_sigma: 0.02,
+            firefly_ratio: 4.0,
+        }
+    }
+}
+
+/// The 1D B3 spline kernel, the 2D one is its outer product.
+const KERNEL: [f32; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+/// The AOVs that guide the filter, any of them may be missing.
+struct Guides<'a> {
+    albedo: Option<&'a [Color]>,
+    normal: Option<&'a [Color]>,
+    depth: Option<&'a [Color]>,
+}
+
+impl Denoiser {
+    /// AOVs the denoiser is guided by, when the framebuffer has them.
+    pub const GUIDES: [Aov; 3] = [Aov::Albedo, Aov::Normal, Aov::Depth];
+
+    /// Denoises the colors of `framebuffer`, guided by whichever of [`Denoiser::GUIDES`] it has.
+    pub fn apply(&self, framebuffer: &mut Framebuffer) {
+        if self.iterations == 0 {
+            return;
+        }
+        let layer = |aov| {
+            framebuffer
+                .aovs
+                .iter()
+                .find(|layer| layer.aov == aov)
+                .map(|layer| &layer.pixels[..])
+        };
+        let guides = Guides {
+            albedo: layer(Aov::Albedo),
+            normal: layer(Aov::Normal),
+            depth: layer(Aov::Depth),
+        };
+
+        // Filter the light arriving at the surfaces rather than the colors, so textures are kept.
+        let albedo = |i: usize| {
+            guides
+                .albedo
+                .map_or(Color::ONE, |albedo| albedo[i].max(Color::splat(0.01)))
+        };
+        let mut light = (0..framebuffer.pixels.len())
+            .map(|i| framebuffer.pixels[i] / albedo(i))
+            .collect::<Vec<_>>();
+        if self.firefly_ratio > 0.0 {
+            light = remove_fireflies(&light, framebuffer.width as usize, self.firefly_ratio);
+        }
+
+        for iteration in 0..self.iterations {
+            let color_sigma = self.color_sigma * 0.5f32.powi(iteration as i32);
+            light = self.filter(
+                &light,
+                &guides,
+                framebuffer.width as usize,
+                1 << iteration,
+                color_sigma,
+            );
+        }
+
+        let pixels = (0..light.len()).map(|i| light[i] * albedo(i)).collect();
+        framebuffer.pixels = pixels;
+    }
+
+    /// One iteration, with taps `step` pixels apart.
+    fn filter(
+        &self,
+        light: &[Color],
+        guides: &Guides,
+        width: usize,
+        step: usize,
+        color_sigma: f32,
+    ) -> Vec<Color> {
+        let height = light.len() / width;
+        let mut out = vec![Color::ZERO; light.len()];
+        out.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+            for (x, out) in row.iter_mut().enumerate() {
+                let p = y * width + x;
+                let (mut sum, mut weight_sum) = (Color::ZERO, 0.0);
+                for (ky, kernel_y) in KERNEL.iter().enumerate() {
+                    let Some(qy) = (y + ky * step).checked_sub(2 * step) else {
+                        continue;
+                    };
+                    for (kx, kernel_x) in KERNEL.iter().enumerate() {
+                        let Some(qx) = (x + kx * step).checked_sub(2 * step) else {
+                            continue;
+                        };
+                        if qx >= width || qy >= height {
+                            continue;
+                        }
+                        let q = qy * width + qx;
+                        let distance = step as f32 * (kx.abs_diff(2).max(ky.abs_diff(2))) as f32;
+                        let weight = kernel_x
+                            * kernel_y
+                            * self.edge_weight(light, guides, p, q, distance, color_sigma);
+                        sum += weight * light[q];
+                        weight_sum += weight;
+                    }
+                }
+                // The pixel itself always has some weight.
+                *out = sum / weight_sum;
+            }
+        });
+        out
+    }
+
+    /// How much pixel `q`, `distance` pixels away, is like pixel `p`.
+    fn edge_weight(
+        &self,
+        light: &[Color],
+        guides: &Guides,
+        p: usize,
+        q: usize,
+        distance: f32,
+        color_sigma: f32,
+    ) -> f32 {
+        let tone_map = |c: Color| c / (Color::ONE + c);
+        let mut exponent = (tone_map(light[p]) - tone_map(light[q])).length_squared()
+            / (color_sigma * color_sigma);
+        if let Some(normal) = guides.normal {
+            exponent += (normal[p] - normal[q]).length_squared() / self.normal_sigma.powi(2);
+        }
+        if let Some(albedo) = guides.albedo {
+            exponent += (albedo[p] - albedo[q]).length_squared() / self.albedo_sigma.powi(2);
+        }
+        if let Some(depth) = guides.depth {
+            let (zp, zq) = (depth[p].x, depth[q].x);
+            let relative = (zp - zq) / (zp.max(f32::EPSILON) * distance.max(1.0));
+            exponent += relative * relative / self.depth_sigma.powi(2);
+        }
+        (-exponent).exp()
+    }
+}
+
+/// Darkens pixels that are more than `ratio` times brighter than all of their neighbors down to
+/// the brightest of them.
+///
+/// Such lone bright pixels are unlike every neighbor, so the edge stopping would keep them.
+/// Pixels without a lit neighbor are kept, as there is nothing to tell them from a small light.
+fn remove_fireflies(light: &[Color], width: usize, ratio: f32) -> Vec<Color> {
+    let height = light.len() / width;
+    let mut out = light.to_vec();
+    out.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+        for (x, out) in row.iter_mut().enumerate() {
+            let mut brightest = 0.0f32;
+            for qy in y.saturating_sub(1)..(y + 2).min(height) {
+                for qx in x.saturating_sub(1)..(x + 2).min(width) {
+                    if (qx, qy) != (x, y) {
+                        brightest = brightest.max(luminance(light[qy * width + qx]));
+                    }
+                }
+            }
+            let own = luminance(*out);
+            if brightest > 0.0 && own > ratio * brightest {
+                *out *= brightest / own;
+            }
+        }
+    });
+    out
+}
@@ -0,0 +1,301 @@
+//! Reconstruction filters, which weight the samples around a pixel by how far they are from its
+//! center.
+//!
+//! Every sample is splatted to all the pixels it is within the filter radius of, and a pixel's
+//! color is the weighted average of the samples it received. The box filter with a radius of half
+//! a pixel only gives samples to the pixel they were taken in.
+
+use std::{f32::consts::PI, str::FromStr};
+
+use anyhow::{bail, Error};
+use glam::Vec2;
+use rayon::{iter::IndexedParallelIterator, iter::ParallelIterator, slice::ParallelSliceMut};
+
+use crate::{checkpoint::Splat, tile::TileGrid, util::color::Color};
+
+pub trait Filter: Sync {
+    /// How far from a pixel's center, in pixels along each axis, samples count for it.
+    fn radius(&self) -> f32;
+    /// Weight of a sample at `offset` pixels from a pixel's center.
+    fn evaluate(&self, offset: Vec2) -> f32;
+}
+
+/// The filters a render can use.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FilterKind {
+    /// Every sample within the radius counts the same.
+    #[default]
+    Box,
+    /// Weights fall off linearly to the radius.
+    Tent,
+    /// A Gaussian with a standard deviation of a third of the radius.
+    Gaussian,
+    /// Mitchell and Netravali's cubic with `B = C = 1/3`.
+    Mitchell,
+    /// A windowed sinc with two lobes.
+    Lanczos,
+}
+
+impl FilterKind {
+    /// The radius the filter is used with unless another is given.
+    pub fn default_radius(self) -> f32 {
+        match self {
+            FilterKind::Box => 0.5,
+            FilterKind::Tent => 1.0,
+            FilterKind::Gaussian => 1.5,
+            FilterKind::Mitchell | FilterKind::Lanczos => 2.0,
+        }
+    }
+
+    pub fn create(self, radius: f32) -> Box<dyn Filter> {
+        match self {
+            FilterKind::Box => Box::new(BoxFilter { radius }),
+            FilterKind::Tent => Box::new(TentFilter { radius }),
+            FilterKind::Gaussian => Box::new(GaussianFilter {
+                radius,
+                sigma: radius / 3.0,
+            }),
+            FilterKind::Mitchell => Box::new(MitchellFilter {
+                radius,
+                b: 1.0 / 3.0,
+                c: 1.0 / 3.0,
+            }),
+            FilterKind::Lanczos => Box::new(LanczosFilter { radius, tau: 2.0 }),
+        }
+    }
+}
+
+impl FromStr for FilterKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "box" => FilterKind::Box,
+            "tent" => FilterKind::Tent,
+            "gaussian" => FilterKind::Gaussian,
+            "mitchell" => FilterKind::Mitchell,
+            "lanczos" => FilterKind::Lanczos,
+            _ => bail!("unknown filter `{s}`, expected box, tent, gaussian, mitchell or lanczos"),
+        })
+    }
+}
+
+pub struct BoxFilter {
+    pub radius: f32,
+}
+
+impl Filter for BoxFilter {
+    fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    fn evaluate(&self, offset: Vec2) -> f32 {
+        if offset.abs().cmple(Vec2::splat(self.radius)).all() {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+pub struct TentFilter {
+    pub radius: f32,
+}
+
+impl Filter for TentFilter {
+    fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    fn evaluate(&self, offset: Vec2) -> f32 {
+        let tent = |x: f32| (self.radius - x.abs()).max(0.0);
+        tent(offset.x) * tent(offset.y)
+    }
+}
+
+pub struct GaussianFilter {
+    pub radius: f32,
+    pub sigma: f32,
+}
+
+impl Filter for GaussianFilter {
+    fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    fn evaluate(&self, offset: Vec2) -> f32 {
+        // Shifted down so it reaches 0 at the radius instead of being cut off.
+        let gaussian = |x: f32| (-x * x / (2.0 * self.sigma * self.sigma)).exp();
+        let edge = gaussian(self.radius);
+        let g = |x: f32| (gaussian(x) - edge).max(0.0);
+        g(offset.x) * g(offset.y)
+    }
+}
+
+pub struct MitchellFilter {
+    pub radius: f32,
+    pub b: f32,
+    pub c: f32,
+}
+
+impl MitchellFilter {
+    /// The cubic, which spans `[-2, 2]`.
+    fn mitchell_1d(&self, x: f32) -> f32 {
+        let (b, c) = (self.b, self.c);
+        let x = x.abs();
+        let value = if x < 1.0 {
+            (12.0 - 9.0 * b - 6.0 * c) * x.powi(3)
+                + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                + (6.0 - 2.0 * b)
+        } else if x < 2.0 {
+            (-b - 6.0 * c) * x.powi(3)
+                + (6.0 * b + 30.0 * c) * x * x
+                + (-12.0 * b - 48.0 * c) * x
+                + (8.0 * b + 24.0 * c)
+        } else {
+            0.0
+        };
+        value / 6.0
+    }
+}
+
+impl Filter for MitchellFilter {
+    fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    fn evaluate(&self, offset: Vec2) -> f32 {
+        let scale = 2.0 / self.radius;
+        self.mitchell_1d(offset.x * scale) * self.mitchell_1d(offset.y * scale)
+    }
+}
+
+pub struct LanczosFilter {
+    pub radius: f32,
+    /// Lobes of the sinc within the radius.
+    pub tau: f32,
+}
+
+impl Filter for LanczosFilter {
+    fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    fn evaluate(&self, offset: Vec2) -> f32 {
+        let sinc = |x: f32| {
+            if x.abs() < 1e-5 {
+                1.0
+            } else {
+                (PI * x).sin() / (PI * x)
+            }
+        };
+        let windowed_sinc = |x: f32| {
+            if x.abs() > self.radius {
+                return 0.0;
+            }
+            let x = x / self.radius * self.tau;
+            sinc(x) * sinc(x / self.tau)
+        };
+        windowed_sinc(offset.x) * windowed_sinc(offset.y)
+    }
+}
+
+/// Where the samples of every tile are splatted: a block of [`Splat`] records per tile, covering
+/// the tile and the `margin` pixels around it that the filter reaches.
+///
+/// Tiles are rendered in parallel, so each gets its own block, and a pixel is resolved by adding
+/// up the blocks that cover it in a fixed order, which keeps the image the same however the tiles
+/// were handed out.
+#[derive(Debug, Clone, Copy)]
+pub struct SplatLayout {
+    pub grid: TileGrid,
+    pub margin: u32,
+}
+
+impl SplatLayout {
+    pub fn new(grid: TileGrid, radius: f32) -> Self {
+        // A sample is at most half a pixel from the center of the pixel it was taken in.
+        let margin = (radius - 0.5).ceil().max(0.0) as u32;
+        Self { grid, margin }
+    }
+
+    /// Width and height of every block, the size of a full tile and its margins.
+    pub fn block_size(&self) -> u32 {
+        self.grid.size + 2 * self.margin
+    }
+
+    pub fn block_bytes(&self) -> usize {
+        (self.block_size() as usize).pow(2) * Splat::BYTES
+    }
+
+    /// Bytes of the blocks of every tile, ordered like the tiles in the grid.
+    pub fn bytes(&self) -> usize {
+        (self.grid.columns() * self.grid.rows()) as usize * self.block_bytes()
+    }
+
+    /// Adds a sample taken `offset` from the center of pixel `(x, y)` of a tile, relative to its
+    /// top left, to the pixels around it in the tile's block.
+    pub fn splat(
+        &self,
+        filter: &dyn Filter,
+        block: &mut [Splat],
+        x: u32,
+        y: u32,
+        offset: Vec2,
+        color: Color,
+    ) {
+        let margin = self.margin as i32;
+        let block_size = self.block_size() as i32;
+        for dy in -margin..=margin {
+            for dx in -margin..=margin {
+                let weight = filter.evaluate(Vec2::new(dx as f32, dy as f32) - offset);
+                if weight == 0.0 {
+                    continue;
+                }
+                let bx = x as i32 + margin + dx;
+                let by = y as i32 + margin + dy;
+                let splat = &mut block[(by * block_size + bx) as usize];
+                splat.sum += weight * color;
+                splat.weight += weight;
+            }
+        }
+    }
+
+    /// The filtered color of every pixel, from the blocks of every tile.
+    pub fn resolve(&self, blocks: &[u8], pixels: &mut [Color]) {
+        let grid = self.grid;
+        let (size, margin) = (grid.size as i64, self.margin as i64);
+        let block_size = self.block_size() as i64;
+        let block_bytes = self.block_bytes();
+        // Tiles whose blocks reach coordinate `x`.
+        let covering = |x: i64, tiles: u32| {
+            let first = ((x - margin - size + 1).max(0) as u64).div_ceil(size as u64) as i64;
+            let last = ((x + margin) / size).min(tiles as i64 - 1);
+            first..=last
+        };
+
+        pixels
+            .par_chunks_mut(grid.width as usize)
+            .enumerate()
+            .for_each(|(y, row)| {
+                let y = y as i64;
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let x = x as i64;
+                    let mut total = Splat::default();
+                    for ty in covering(y, grid.rows()) {
+                        for tx in covering(x, grid.columns()) {
+                            let index = (ty * grid.columns() as i64 + tx) as usize;
+                            let bx = x - tx * size + margin;
+                            let by = y - ty * size + margin;
+                            let offset = index * block_bytes
+                                + (by * block_size + bx) as usize * Splat::BYTES;
+                            let splat = Splat::read(&blocks[offset..offset + Splat::BYTES]);
+                            total.sum += splat.sum;
+                            total.weight += splat.weight;
+                        }
+                    }
+                    *pixel = total.color();
+                }
+            });
+    }
+}
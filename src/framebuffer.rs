@@ -0,0 +1,216 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use exr::prelude::{
+    AnyChannel, AnyChannels, Encoding, FlatSamples, Image, Layer, LayerAttributes, SmallVec,
+    WritableImage,
+};
+use image::{codecs::hdr::HdrEncoder, ImageFormat, Rgb, Rgb32FImage, RgbImage};
+
+use crate::{
+    aov::AovLayer,
+    util::color::{Color, DisplayTransform, ToneMap, Transfer},
+};
+
+/// A rendered image of linear colors, stored row by row from the top left.
+#[derive(Debug, Clone)]
+pub struct Framebuffer {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Color>,
+    /// How the linear colors are turned into 8-bit ones when saving to a non HDR format.
+    pub display: DisplayTransform,
+    /// Images of what the pixels see first, saved along with the colors.
+    pub aovs: Vec<AovLayer>,
+}
+
+impl Framebuffer {
+    /// A black image of the given size.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Color::ZERO; (width * height) as usize],
+            display: DisplayTransform::default(),
+            aovs: vec![],
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Color {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, color: Color) {
+        self.pixels[(y * self.width + x) as usize] = color;
+    }
+
+    /// Tone maps and quantizes every pixel for display with `self.display`.
+    pub fn to_rgb8(&self) -> RgbImage {
+        let bytes = self
+            .pixels
+            .iter()
+            .flat_map(|&pixel| self.display.apply(pixel))
+            .collect();
+        RgbImage::from_raw(self.width, self.height, bytes).expect("Framebuffer size mismatch!")
+    }
+
+    /// The linear colors as they are, for formats that can store radiance above 1.0.
+    pub fn to_rgb32f(&self) -> Rgb32FImage {
+        let floats = self
+            .pixels
+            .iter()
+            .flat_map(|pixel| pixel.to_array())
+            .collect();
+        Rgb32FImage::from_raw(self.width, self.height, floats).expect("Framebuffer size mismatch!")
+    }
+
+    /// Writes the image, picking the format from the extension of `path`.
+    ///
+    /// `.exr`, `.hdr` and `.pfm` files keep the linear colors, anything else goes through
+    /// `self.display` to 8 bits.
+    ///
+    /// The AOVs become layers of an `.exr` file. With other formats each is written to its own
+    /// file next to `path`, named like `image.albedo.png`, and is made viewable first if the
+    /// format isn't HDR.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        if extension == "exr" && !self.aovs.is_empty() {
+            return self.save_exr_layers(path);
+        }
+
+        self.save_colors(path, &extension)?;
+
+        let hdr = matches!(extension.as_str(), "hdr" | "pfm");
+        for layer in &self.aovs {
+            let name = layer.aov.name();
+            let aov_path = match path.extension().and_then(|ext| ext.to_str()) {
+                Some(ext) => path.with_extension(format!("{name}.{ext}")),
+                None => path.with_extension(name),
+            };
+            self.aov_image(layer, hdr).save(aov_path)?;
+        }
+        Ok(())
+    }
+
+    fn save_colors(&self, path: &Path, extension: &str) -> Result<()> {
+        if extension == "pfm" {
+            return self.save_pfm(path);
+        }
+
+        match ImageFormat::from_extension(extension) {
+            Some(format) => self.save_with_format(path, format),
+            None => self
+                .to_rgb8()
+                .save(path)
+                .with_context(|| format!("Failed to write image {}", path.display())),
+        }
+    }
+
+    pub fn save_with_format(&self, path: impl AsRef<Path>, format: ImageFormat) -> Result<()> {
+        let path = path.as_ref();
+        let result = match format {
+            ImageFormat::OpenExr => self.to_rgb32f().save_with_format(path, format),
+            ImageFormat::Hdr => self.write_hdr(create(path)?),
+            _ => self.to_rgb8().save_with_format(path, format),
+        };
+        result.with_context(|| format!("Failed to write image {}", path.display()))
+    }
+
+    /// An AOV as an image of its own, with values mapped to colors for display unless `hdr`.
+    fn aov_image(&self, layer: &AovLayer, hdr: bool) -> Framebuffer {
+        Framebuffer {
+            pixels: if hdr {
+                layer.pixels.clone()
+            } else {
+                layer.aov.visualize(&layer.pixels)
+            },
+            display: DisplayTransform {
+                exposure: 0.0,
+                tone_map: ToneMap::Clamp,
+                transfer: Transfer::Linear,
+            },
+            ..Framebuffer::new(self.width, self.height)
+        }
+    }
+
+    /// Writes the colors and AOVs as channels of a single EXR image, the AOVs named like
+    /// `albedo.R` or `depth.Z`.
+    fn save_exr_layers(&self, path: &Path) -> Result<()> {
+        let channel = |name: String, values: &[Color], index: usize| {
+            let samples = values.iter().map(|value| value[index]).collect();
+            AnyChannel::new(name.as_str(), FlatSamples::F32(samples))
+        };
+
+        let mut channels = ["R", "G", "B"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| channel(name.to_string(), &self.pixels, i))
+            .collect::<Vec<_>>();
+        for layer in &self.aovs {
+            for (i, name) in layer.aov.channels().iter().enumerate() {
+                let name = format!("{}.{name}", layer.aov.name());
+                channels.push(channel(name, &layer.pixels, i));
+            }
+        }
+
+        let layer = Layer::new(
+            (self.width as usize, self.height as usize),
+            LayerAttributes::default(),
+            Encoding::FAST_LOSSLESS,
+            AnyChannels::sort(SmallVec::from_vec(channels)),
+        );
+        Image::from_layer(layer)
+            .write()
+            .to_file(path)
+            .with_context(|| format!("Failed to write image {}", path.display()))
+    }
+
+    /// Writes the linear colors as a Radiance RGBE image.
+    fn write_hdr(&self, out: impl Write) -> image::ImageResult<()> {
+        let pixels = self
+            .pixels
+            .iter()
+            .map(|pixel| Rgb(pixel.to_array()))
+            .collect::<Vec<_>>();
+        HdrEncoder::new(out).encode(&pixels, self.width as usize, self.height as usize)
+    }
+
+    /// Writes the linear colors as a little endian Portable Float Map.
+    pub fn save_pfm(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        self.write_pfm(create(path)?)
+            .with_context(|| format!("Failed to write image {}", path.display()))
+    }
+
+    fn write_pfm(&self, mut out: impl Write) -> std::io::Result<()> {
+        // A negative scale marks the data as little endian.
+        write!(out, "PF\n{} {}\n-1.0\n", self.width, self.height)?;
+
+        // PFM rows go from the bottom of the image to the top.
+        for row in self.pixels.chunks(self.width as usize).rev() {
+            for pixel in row {
+                for c in pixel.to_array() {
+                    out.write_all(&c.to_le_bytes())?;
+                }
+            }
+        }
+
+        out.flush()
+    }
+}
+
+fn create(path: &Path) -> Result<BufWriter<File>> {
+    let file =
+        File::create(path).with_context(|| format!("Failed to create image {}", path.display()))?;
+    Ok(BufWriter::new(file))
+}
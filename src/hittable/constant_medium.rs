@@ -1,4 +1,4 @@
-use std::{f32::INFINITY, sync::Arc};
+use std::sync::Arc;
 
 use glam::Vec3A;
 
@@ -28,7 +28,7 @@ impl ConstantMedium {
 }
 
 impl Hittable for ConstantMedium {
-    fn hit<'mat>(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
         // Occasional debugging.
         const ENABLE_DEBUGGING: bool = false;
         let debugging: bool = ENABLE_DEBUGGING && random_f32(0.0, 1.0) < 0.00001;
@@ -36,7 +36,7 @@ impl Hittable for ConstantMedium {
         if let Some(mut hit1) = self.boundary.hit(r, Interval::UNIVERSE) {
             if let Some(mut hit2) = self
                 .boundary
-                .hit(r, Interval::new(hit1.t + 0.0001, INFINITY))
+                .hit(r, Interval::new(hit1.t + 0.0001, f32::INFINITY))
             {
                 if debugging {
                     eprintln!("ray_tmin={} ray_tmax={}", hit1.t, hit2.t);
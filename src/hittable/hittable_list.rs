@@ -1,8 +1,11 @@
 use std::sync::Arc;
 
+use glam::Vec3A;
+
 use crate::{
     bvh::aabb::AABB,
-    util::{interval::Interval, ray::Ray},
+    sampler::Sampler,
+    util::{interval::Interval, ray::Ray, Point3},
 };
 
 use super::{HitRecord, Hittable};
@@ -28,13 +31,22 @@ impl HittableList {
     }
 
     pub fn add(&mut self, object: impl Hittable + 'static) {
+        self.add_shared(Arc::new(object));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Adds an object that may also be referenced elsewhere.
+    pub fn add_shared(&mut self, object: Arc<dyn Hittable>) {
         self.bbox.expand(object.bounding_box());
-        self.objects.push(Arc::new(object));
+        self.objects.push(object);
     }
 }
 
 impl Hittable for HittableList {
-    fn hit(&self, r: &Ray, mut ray_t: Interval) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, mut ray_t: Interval) -> Option<HitRecord<'_>> {
         let mut f_rec = None;
         let mut closest = ray_t.max;
 
@@ -52,4 +64,25 @@ impl Hittable for HittableList {
     fn bounding_box(&self) -> &AABB {
         &self.bbox
     }
+
+    /// The average of the densities of the objects, which are picked with equal chance.
+    fn pdf_value(&self, origin: Point3, direction: Vec3A) -> f32 {
+        let weight = 1.0 / self.objects.len() as f32;
+        self.objects
+            .iter()
+            .map(|object| weight * object.pdf_value(origin, direction))
+            .sum()
+    }
+
+    fn random(&self, origin: Point3, sampler: &mut dyn Sampler) -> Vec3A {
+        let index = (sampler.get_1d() * self.objects.len() as f32) as usize;
+        self.objects[index.min(self.objects.len() - 1)].random(origin, sampler)
+    }
+
+    fn traversal_cost(&self, r: &Ray, ray_t: Interval) -> u32 {
+        self.objects
+            .iter()
+            .map(|object| object.traversal_cost(r, ray_t))
+            .sum()
+    }
 }
@@ -1,123 +1,160 @@
 use std::sync::Arc;
 
-use glam::Vec3A;
+use glam::{Affine3A, Mat3A, Vec3, Vec3A};
 
-use crate::bvh::aabb::AABB;
+use crate::{bvh::aabb::AABB, material::Material, sampler::Sampler};
 
 use super::{HitRecord, Hittable};
 use crate::util::all::*;
 
-pub struct Translate {
+/// An object moved by an affine transform, which can translate, rotate, scale and shear it.
+///
+/// Start from [`Transform::new`] and chain [`Transform::scale`], [`Transform::rotate`],
+/// [`Transform::translate`] and [`Transform::look_at`], each of which happens after the ones
+/// before it.
+///
+/// Many transforms can share one object, such as a [`crate::bvh::node::BVHNode`] of a mesh, with
+/// [`Transform::shared`], so every copy of it in a scene costs only the transform.
+pub struct Transform {
     object: Arc<dyn Hittable>,
-    offset: Vec3A,
+    /// Material of every hit instead of the one of the object, if any.
+    material: Option<Material>,
+    /// From the space of the object to the world.
+    to_world: Affine3A,
+    /// From the world to the space of the object.
+    to_object: Affine3A,
+    /// The inverse transpose of the linear part of `to_world`, which keeps normals
+    /// perpendicular to the surface when it is scaled unevenly or sheared.
+    normal_to_world: Mat3A,
     bbox: AABB,
 }
 
-impl Translate {
-    pub fn new(object: impl Hittable + 'static, offset: Vec3A) -> Self {
+impl Transform {
+    /// The object where it is.
+    pub fn new(object: impl Hittable + 'static) -> Self {
+        Self::shared(Arc::new(object))
+    }
+
+    /// An object other transforms may also place, where it is.
+    pub fn shared(object: Arc<dyn Hittable>) -> Self {
         Self {
-            bbox: object.bounding_box().clone() + offset,
-            object: Arc::new(object),
-            offset,
+            bbox: object.bounding_box().clone(),
+            object,
+            material: None,
+            to_world: Affine3A::IDENTITY,
+            to_object: Affine3A::IDENTITY,
+            normal_to_world: Mat3A::IDENTITY,
         }
     }
-}
 
-impl Hittable for Translate {
-    fn hit<'mat>(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
-        let offset_r = Ray::new_with_time(r.origin - self.offset, r.direction, r.time);
+    /// The object moved by `to_world`, which must be invertible.
+    pub fn from_affine(object: impl Hittable + 'static, to_world: Affine3A) -> Self {
+        Self::new(object).then(to_world)
+    }
 
-        self.object.hit(&offset_r, ray_t).map(|mut rec| {
-            rec.p += self.offset;
-            rec
-        })
+    /// Shades the object with `material` instead of its own.
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = Some(material);
+        self
     }
 
-    fn bounding_box(&self) -> &AABB {
-        &self.bbox
+    /// Moves the object by `offset`.
+    pub fn translate(self, offset: Vec3A) -> Self {
+        self.then(Affine3A::from_translation(offset.into()))
     }
-}
 
-pub struct Rotation<const T: usize> {
-    object: Arc<dyn Hittable>,
-    bbox: AABB,
-    sin_theta: f32,
-    cos_theta: f32,
-}
+    /// Rotates the object by `angle_degrees` counterclockwise about `axis` through the origin.
+    pub fn rotate(self, axis: Vec3A, angle_degrees: f32) -> Self {
+        let axis = Vec3::from(axis.normalize());
+        self.then(Affine3A::from_axis_angle(axis, angle_degrees.to_radians()))
+    }
 
-impl Rotation<AXIS_Y> {
-    pub fn new(object: impl Hittable + 'static, angle_degrees: f32) -> Self {
-        let radians = angle_degrees.to_radians();
-        let (sin_theta, cos_theta) = radians.sin_cos();
-        let bbox = object.bounding_box();
-
-        let mut min = Point3::INFINITY;
-        let mut max = Point3::NEG_INFINITY;
-
-        for i in 0..2 {
-            for j in 0..2 {
-                for k in 0..2 {
-                    let x = i as f32 * bbox.x.max + (1.0 - i as f32) * bbox.x.min;
-                    let y = j as f32 * bbox.y.max + (1.0 - j as f32) * bbox.y.min;
-                    let z = k as f32 * bbox.z.max + (1.0 - k as f32) * bbox.z.min;
-
-                    let new_x = cos_theta * x + sin_theta * z;
-                    let new_z = -sin_theta * x + cos_theta * z;
-
-                    let tester = Vec3A::new(new_x, y, new_z);
-
-                    for a in 0..3 {
-                        min[a] = min[a].min(tester[a]);
-                        max[a] = max[a].max(tester[a]);
-                    }
-                }
-            }
-        }
+    /// Scales the object along each axis away from the origin.
+    pub fn scale(self, scale: Vec3A) -> Self {
+        self.then(Affine3A::from_scale(scale.into()))
+    }
 
-        Self {
-            object: Arc::new(object),
-            bbox: AABB::from_points(min, max),
-            sin_theta,
-            cos_theta,
-        }
+    /// Moves the origin of the object to `from` and turns it so its -Z axis points at `to`, with
+    /// its +Y axis as close to `up` as it can be, the way cameras are placed.
+    pub fn look_at(self, from: Point3, to: Point3, up: Vec3A) -> Self {
+        let view = Affine3A::look_at_rh(from.into(), to.into(), up.into());
+        self.then(view.inverse())
+    }
+
+    /// Applies `transform` after the current one.
+    pub fn then(mut self, transform: Affine3A) -> Self {
+        self.set(transform * self.to_world);
+        self
+    }
+
+    fn set(&mut self, to_world: Affine3A) {
+        self.to_world = to_world;
+        self.to_object = to_world.inverse();
+        self.normal_to_world = self.to_object.matrix3.transpose();
+        self.bbox = transform_box(self.object.bounding_box(), to_world);
+    }
+
+    fn object_ray(&self, r: &Ray) -> Ray {
+        // The direction isn't normalized, so distances along the ray stay the same in both spaces.
+        Ray::new_with_time(
+            self.to_object.transform_point3a(r.origin),
+            self.to_object.transform_vector3a(r.direction),
+            r.time,
+        )
     }
 }
 
-impl Hittable for Rotation<AXIS_Y> {
-    fn hit<'mat>(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
-        // Change ray from world to object space
-        let mut origin = r.origin;
-        let mut direction = r.direction;
-
-        origin.x = self.cos_theta * r.origin.x - self.sin_theta * r.origin.z;
-        origin.z = self.sin_theta * r.origin.x + self.cos_theta * r.origin.z;
-
-        direction.x = self.cos_theta * r.direction.x - self.sin_theta * r.direction.z;
-        direction.z = self.sin_theta * r.direction.x + self.cos_theta * r.direction.z;
-
-        let rotated_r = Ray::new_with_time(origin, direction, r.time);
-
-        // Determine where an intersection occurs in object space
-        if let Some(mut hit) = self.object.hit(&rotated_r, ray_t) {
-            // Change the intersection point to world space
-            let mut p = hit.p;
-            p.x = self.cos_theta * hit.p.x + self.sin_theta * hit.p.z;
-            p.z = -self.sin_theta * hit.p.x + self.cos_theta * hit.p.z;
-            hit.p = p;
-
-            // Change the normal to world space
-            let mut normal = hit.normal;
-            normal.x = self.cos_theta * hit.normal.x + self.sin_theta * hit.normal.z;
-            normal.z = -self.sin_theta * hit.normal.x + self.cos_theta * hit.normal.z;
-            hit.normal = normal;
-
-            Some(hit)
-        } else {
-            None
+/// The bounding box of the corners of `bbox` moved by `transform`.
+fn transform_box(bbox: &AABB, transform: Affine3A) -> AABB {
+    let mut min = Point3::INFINITY;
+    let mut max = Point3::NEG_INFINITY;
+    for i in 0..8 {
+        let corner = Point3::new(
+            if i & 1 == 0 { bbox.x.min } else { bbox.x.max },
+            if i & 2 == 0 { bbox.y.min } else { bbox.y.max },
+            if i & 4 == 0 { bbox.z.min } else { bbox.z.max },
+        );
+        let corner = transform.transform_point3a(corner);
+        min = min.min(corner);
+        max = max.max(corner);
+    }
+    AABB::from_points(min, max)
+}
+
+impl Hittable for Transform {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let mut rec = self.object.hit(&self.object_ray(r), ray_t)?;
+        rec.p = self.to_world.transform_point3a(rec.p);
+        // The normal still faces against the ray, as the inverse transpose keeps the sign of its
+        // dot product with transformed directions.
+        rec.normal = (self.normal_to_world * rec.normal).normalize();
+        if let Some(material) = &self.material {
+            rec.material = material;
         }
+        Some(rec)
     }
 
     fn bounding_box(&self) -> &AABB {
         &self.bbox
     }
+
+    fn traversal_cost(&self, r: &Ray, ray_t: Interval) -> u32 {
+        self.object.traversal_cost(&self.object_ray(r), ray_t)
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3A) -> f32 {
+        // The object's density is per solid angle in its own space. A unit direction w of the
+        // world becomes A w there, A the linear part of `to_object`, and solid angles around it
+        // are scaled by |det A| / |A w|^3.
+        let direction = self.to_object.transform_vector3a(direction.normalize());
+        let jacobian = self.to_object.matrix3.determinant().abs() / direction.length().powi(3);
+        let origin = self.to_object.transform_point3a(origin);
+        self.object.pdf_value(origin, direction) * jacobian
+    }
+
+    fn random(&self, origin: Point3, sampler: &mut dyn Sampler) -> Vec3A {
+        let origin = self.to_object.transform_point3a(origin);
+        self.to_world
+            .transform_vector3a(self.object.random(origin, sampler))
+    }
 }
@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use glam::{Vec2, Vec3A};
+
+use crate::{
+    bvh::{aabb::AABB, node::BVHNode},
+    material::Material,
+    sampler::Sampler,
+    util::{color::Color, interval::Interval, ray::Ray, Point3},
+};
+
+use super::{hittable_list::HittableList, triangle, HitRecord, Hittable};
+
+/// Triangles that share vertex buffers, indexed the same way for positions, normals, UVs and
+/// colors.
+///
+/// Add the triangles to a scene with [`TriangleMesh::triangles`], or as a BVH of their own with
+/// [`TriangleMesh::into_bvh`].
+#[derive(Clone, Default)]
+pub struct TriangleMesh {
+    pub positions: Vec<Point3>,
+    /// A normal for every position to interpolate for smooth shading, or none for flat shading.
+    pub normals: Vec<Vec3A>,
+    /// UVs for every position, or none to use the barycentric coordinates of hits.
+    pub uvs: Vec<Vec2>,
+    /// A color for every position, or none. A [`VertexColors`](crate::texture::VertexColors)
+    /// texture of them blends them across the faces.
+    pub colors: Arc<[Color]>,
+    /// Indices of the vertices of every face.
+    pub indices: Vec<[u32; 3]>,
+    pub materials: Vec<Material>,
+    /// Index into `materials` for every face, or none to use the first material for them all.
+    pub face_materials: Vec<u32>,
+}
+
+impl TriangleMesh {
+    pub fn new(positions: Vec<Point3>, indices: Vec<[u32; 3]>, material: Material) -> Self {
+        Self {
+            positions,
+            indices,
+            materials: vec![material],
+            ..Default::default()
+        }
+    }
+
+    /// Checks that every index is in range and that the optional buffers are complete.
+    pub fn validate(&self) -> Result<()> {
+        let vertices = self.positions.len();
+        if !self.normals.is_empty() && self.normals.len() != vertices {
+            bail!(
+                "mesh has {} normals for {vertices} vertices",
+                self.normals.len()
+            );
+        }
+        if !self.uvs.is_empty() && self.uvs.len() != vertices {
+            bail!("mesh has {} UVs for {vertices} vertices", self.uvs.len());
+        }
+        if !self.colors.is_empty() && self.colors.len() != vertices {
+            bail!(
+                "mesh has {} colors for {vertices} vertices",
+                self.colors.len()
+            );
+        }
+        if let Some(index) = self
+            .indices
+            .iter()
+            .flatten()
+            .find(|&&i| i as usize >= vertices)
+        {
+            bail!("mesh has a face with vertex {index}, but only {vertices} vertices");
+        }
+
+        if self.materials.is_empty() {
+            bail!("mesh has no materials");
+        }
+        if !self.face_materials.is_empty() {
+            if self.face_materials.len() != self.indices.len() {
+                bail!(
+                    "mesh has {} face materials for {} faces",
+                    self.face_materials.len(),
+                    self.indices.len()
+                );
+            }
+            if let Some(index) = self
+                .face_materials
+                .iter()
+                .find(|&&i| i as usize >= self.materials.len())
+            {
+                bail!(
+                    "mesh has a face with material {index}, but only {} materials",
+                    self.materials.len()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the normals to the area weighted average of the normals of the faces around every
+    /// vertex, so the mesh is shaded smoothly.
+    pub fn compute_normals(&mut self) {
+        let mut normals = vec![Vec3A::ZERO; self.positions.len()];
+        for face in &self.indices {
+            let [a, b, c] = face.map(|i| self.positions[i as usize]);
+            // The length of the cross product is twice the area of the face.
+            let n = (b - a).cross(c - a);
+            for &i in face {
+                normals[i as usize] += n;
+            }
+        }
+        self.normals = normals.into_iter().map(Vec3A::normalize_or_zero).collect();
+    }
+
+    /// A hittable for every face that isn't degenerate.
+    pub fn triangles(self: &Arc<Self>) -> HittableList {
+        self.triangles_where(|_| true)
+    }
+
+    /// The triangles with an emissive material, to sample as lights.
+    pub fn lights(self: &Arc<Self>) -> HittableList {
+        self.triangles_where(Material::is_emissive)
+    }
+
+    fn triangles_where(self: &Arc<Self>, include: impl Fn(&Material) -> bool) -> HittableList {
+        let mut list = HittableList::new();
+        for face in 0..self.indices.len() {
+            let vertices = self.vertices(face);
+            let n = (vertices[1] - vertices[0]).cross(vertices[2] - vertices[0]);
+            if n.length_squared() == 0.0 || !include(self.material(face)) {
+                continue;
+            }
+            list.add(MeshTriangle {
+                mesh: self.clone(),
+                face,
+                bbox: triangle::bounding_box(&vertices),
+            });
+        }
+        list
+    }
+
+    /// The triangles of the mesh in a BVH of their own.
+    pub fn into_bvh(self) -> BVHNode {
+        BVHNode::from_list(&Arc::new(self).triangles())
+    }
+
+    fn vertices(&self, face: usize) -> [Point3; 3] {
+        self.indices[face].map(|i| self.positions[i as usize])
+    }
+
+    fn material(&self, face: usize) -> &Material {
+        match self.face_materials.get(face) {
+            Some(&index) => &self.materials[index as usize],
+            None => &self.materials[0],
+        }
+    }
+}
+
+/// A face of a [`TriangleMesh`].
+pub struct MeshTriangle {
+    mesh: Arc<TriangleMesh>,
+    face: usize,
+    bbox: AABB,
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let mesh = &*self.mesh;
+        let vertices = mesh.vertices(self.face);
+        let (t, barycentric) = triangle::intersect(r, ray_t, &vertices)?;
+
+        let indices = mesh.indices[self.face].map(|i| i as usize);
+        let normals = (!mesh.normals.is_empty()).then(|| indices.map(|i| mesh.normals[i]));
+        let uvs = (!mesh.uvs.is_empty()).then(|| indices.map(|i| mesh.uvs[i]));
+        let mut rec = triangle::hit_record(
+            r,
+            t,
+            barycentric,
+            &vertices,
+            normals,
+            uvs,
+            mesh.material(self.face),
+        );
+        rec.vertices = Some((mesh.indices[self.face], barycentric));
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> &AABB {
+        &self.bbox
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3A) -> f32 {
+        triangle::pdf_value(&self.mesh.vertices(self.face), origin, direction)
+    }
+
+    fn random(&self, origin: Point3, sampler: &mut dyn Sampler) -> Vec3A {
+        triangle::sample(&self.mesh.vertices(self.face), sampler.get_2d()) - origin
+    }
+}
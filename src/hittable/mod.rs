@@ -1,8 +1,11 @@
+use std::sync::Arc;
+
 use glam::Vec3A;
 
 use crate::{
     bvh::aabb::AABB,
     material::Material,
+    sampler::Sampler,
     util::{interval::Interval, ray::Ray, Point3},
 };
 
@@ -11,8 +14,11 @@ use self::{hittable_list::HittableList, quad::Quad};
 pub mod constant_medium;
 pub mod hittable_list;
 pub mod instance;
+pub mod mesh;
 pub mod quad;
 pub mod sphere;
+pub mod tagged;
+pub mod triangle;
 
 pub struct HitRecord<'mat> {
     /// Absolute point of the hit
@@ -28,6 +34,13 @@ pub struct HitRecord<'mat> {
     pub v: f32,
     /// Whether or not the normal is an outward facing face.
     pub front_face: bool,
+    /// Which object of the scene was hit, 0 if unknown. See [`tagged::Tagged`].
+    pub object_id: u32,
+    /// Which material of the scene was hit, 0 if unknown.
+    pub material_id: u32,
+    /// Indices of the corners of the mesh face that was hit and their barycentric weights at
+    /// the hit, for textures that blend values of the vertices.
+    pub vertices: Option<([u32; 3], Vec3A)>,
 }
 
 impl<'mat> HitRecord<'mat> {
@@ -53,13 +66,53 @@ impl<'mat> HitRecord<'mat> {
             u,
             v,
             front_face,
+            object_id: 0,
+            material_id: 0,
+            vertices: None,
         }
     }
 }
 
 pub trait Hittable: Sync + Send {
-    fn hit<'mat>(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord>;
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>>;
     fn bounding_box(&self) -> &AABB;
+
+    /// Density, per unit solid angle, of [`Hittable::random`] picking `direction` from `origin`.
+    fn pdf_value(&self, _origin: Point3, _direction: Vec3A) -> f32 {
+        0.0
+    }
+
+    /// A random direction from `origin` towards the object.
+    fn random(&self, _origin: Point3, _sampler: &mut dyn Sampler) -> Vec3A {
+        Vec3A::X
+    }
+
+    /// Number of bounding boxes and objects `r` is tested against to find its hit.
+    fn traversal_cost(&self, _r: &Ray, _ray_t: Interval) -> u32 {
+        1
+    }
+}
+
+impl<T: Hittable + ?Sized> Hittable for Arc<T> {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        (**self).hit(r, ray_t)
+    }
+
+    fn bounding_box(&self) -> &AABB {
+        (**self).bounding_box()
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3A) -> f32 {
+        (**self).pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Point3, sampler: &mut dyn Sampler) -> Vec3A {
+        (**self).random(origin, sampler)
+    }
+
+    fn traversal_cost(&self, r: &Ray, ray_t: Interval) -> u32 {
+        (**self).traversal_cost(r, ray_t)
+    }
 }
 
 /// Returns a 3D box that contains the two opposite verticies, a and b.
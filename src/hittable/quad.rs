@@ -1,6 +1,6 @@
 use glam::Vec3A;
 
-use crate::{bvh::aabb::AABB, material::Material, util::Point3};
+use crate::{bvh::aabb::AABB, material::Material, sampler::Sampler, util::Point3};
 
 use super::{HitRecord, Hittable};
 use crate::util::all::*;
@@ -15,6 +15,7 @@ pub struct Quad {
     normal: Vec3A,
     d: f32,
     w: Vec3A,
+    area: f32,
 }
 
 impl Quad {
@@ -31,6 +32,7 @@ impl Quad {
             normal,
             d: normal.dot(q),
             w: n / n.dot(n),
+            area: n.length(),
         };
         s.update_bounding_box();
         s
@@ -41,12 +43,12 @@ impl Quad {
     }
 
     fn is_interior(&self, a: f32, b: f32) -> bool {
-        (a >= 0.0) && (a <= 1.0) && (b >= 0.0) && (b <= 1.0)
+        (0.0..=1.0).contains(&a) && (0.0..=1.0).contains(&b)
     }
 }
 
 impl Hittable for Quad {
-    fn hit<'mat>(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
         let denom = self.normal.dot(r.direction);
 
         // r is parallel
@@ -84,4 +86,23 @@ impl Hittable for Quad {
     fn bounding_box(&self) -> &AABB {
         &self.bbox
     }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3A) -> f32 {
+        let Some(rec) = self.hit(
+            &Ray::new(origin, direction),
+            Interval::new(0.001, f32::INFINITY),
+        ) else {
+            return 0.0;
+        };
+
+        let distance_squared = rec.t * rec.t * direction.length_squared();
+        let cosine = (direction.dot(self.normal) / direction.length()).abs();
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: Point3, sampler: &mut dyn Sampler) -> Vec3A {
+        let s = sampler.get_2d();
+        let p = self.q + (s.x * self.u) + (s.y * self.v);
+        p - origin
+    }
 }
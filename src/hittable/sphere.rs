@@ -1,11 +1,12 @@
 use std::f32::consts::PI;
 
-use glam::Vec3A;
+use glam::{Vec2, Vec3A};
 
 use crate::{
     bvh::aabb::AABB,
     material::Material,
-    util::{all::Ray, interval::Interval, Point3},
+    sampler::Sampler,
+    util::{all::Ray, interval::Interval, onb::ONB, Point3},
 };
 
 use super::{HitRecord, Hittable};
@@ -55,6 +56,15 @@ impl Sphere {
         self.origin + time * self.movement_vec
     }
 
+    /// A direction inside the cone of a sphere seen from `distance_squared` away, around +Z.
+    fn random_to_sphere(radius: f32, distance_squared: f32, u: Vec2) -> Vec3A {
+        let z = 1.0 + u.y * ((1.0 - radius * radius / distance_squared).max(0.0).sqrt() - 1.0);
+
+        let phi = 2.0 * PI * u.x;
+        let sin_theta = (1.0 - z * z).max(0.0).sqrt();
+        Vec3A::new(phi.cos() * sin_theta, phi.sin() * sin_theta, z)
+    }
+
     fn get_sphere_uv(p: Point3) -> (f32, f32) {
         // p: a given point on the sphere of radius one, centered at the origin.
         // u: returned value [0,1] of angle around the Y axis from X=-1.
@@ -71,7 +81,7 @@ impl Sphere {
 }
 
 impl Hittable for Sphere {
-    fn hit<'mat>(&'mat self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
         let position = if self.is_moving {
             self.position(r.time)
         } else {
@@ -114,4 +124,35 @@ impl Hittable for Sphere {
     fn bounding_box(&self) -> &AABB {
         &self.bbox
     }
+
+    /// Only correct for spheres that don't move, moving ones are treated as if they were at
+    /// their starting position.
+    fn pdf_value(&self, origin: Point3, direction: Vec3A) -> f32 {
+        if self
+            .hit(
+                &Ray::new(origin, direction),
+                Interval::new(0.001, f32::INFINITY),
+            )
+            .is_none()
+        {
+            return 0.0;
+        }
+
+        let cos_theta_max = (1.0
+            - self.radius * self.radius / (self.origin - origin).length_squared())
+        .max(0.0)
+        .sqrt();
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+        1.0 / solid_angle
+    }
+
+    fn random(&self, origin: Point3, sampler: &mut dyn Sampler) -> Vec3A {
+        let direction = self.origin - origin;
+        let uvw = ONB::new_from_w(&direction);
+        uvw.local(Self::random_to_sphere(
+            self.radius,
+            direction.length_squared(),
+            sampler.get_2d(),
+        ))
+    }
 }
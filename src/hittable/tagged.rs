@@ -0,0 +1,58 @@
+use glam::Vec3A;
+
+use crate::{
+    bvh::aabb::AABB,
+    sampler::Sampler,
+    util::{interval::Interval, ray::Ray, Point3},
+};
+
+use super::{HitRecord, Hittable};
+
+/// Marks the hits of an object with ids of the object and its material, so they can be told
+/// apart in the AOVs.
+///
+/// An id of 0 leaves the one set by the object, if any, as it is.
+pub struct Tagged<T> {
+    object: T,
+    object_id: u32,
+    material_id: u32,
+}
+
+impl<T: Hittable> Tagged<T> {
+    pub fn new(object: T, object_id: u32, material_id: u32) -> Self {
+        Self {
+            object,
+            object_id,
+            material_id,
+        }
+    }
+}
+
+impl<T: Hittable> Hittable for Tagged<T> {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let mut rec = self.object.hit(r, ray_t)?;
+        if self.object_id != 0 {
+            rec.object_id = self.object_id;
+        }
+        if self.material_id != 0 {
+            rec.material_id = self.material_id;
+        }
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> &AABB {
+        self.object.bounding_box()
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3A) -> f32 {
+        self.object.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Point3, sampler: &mut dyn Sampler) -> Vec3A {
+        self.object.random(origin, sampler)
+    }
+
+    fn traversal_cost(&self, r: &Ray, ray_t: Interval) -> u32 {
+        self.object.traversal_cost(r, ray_t)
+    }
+}
@@ -0,0 +1,189 @@
+use glam::{Vec2, Vec3A};
+
+use crate::{
+    bvh::aabb::AABB,
+    material::Material,
+    sampler::Sampler,
+    util::{interval::Interval, ray::Ray, Point3},
+};
+
+use super::{HitRecord, Hittable};
+
+/// A single triangle, see [`super::mesh::TriangleMesh`] for many that share their vertices.
+pub struct Triangle {
+    vertices: [Point3; 3],
+    /// Normals at the vertices, interpolated for smooth shading.
+    normals: Option<[Vec3A; 3]>,
+    uvs: Option<[Vec2; 3]>,
+    material: Material,
+    bbox: AABB,
+}
+
+impl Triangle {
+    pub fn new(a: Point3, b: Point3, c: Point3, material: Material) -> Self {
+        let vertices = [a, b, c];
+        Self {
+            vertices,
+            normals: None,
+            uvs: None,
+            material,
+            bbox: bounding_box(&vertices),
+        }
+    }
+
+    pub fn with_normals(mut self, normals: [Vec3A; 3]) -> Self {
+        self.normals = Some(normals);
+        self
+    }
+
+    pub fn with_uvs(mut self, uvs: [Vec2; 3]) -> Self {
+        self.uvs = Some(uvs);
+        self
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        let (t, barycentric) = intersect(r, ray_t, &self.vertices)?;
+        Some(hit_record(
+            r,
+            t,
+            barycentric,
+            &self.vertices,
+            self.normals,
+            self.uvs,
+            &self.material,
+        ))
+    }
+
+    fn bounding_box(&self) -> &AABB {
+        &self.bbox
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3A) -> f32 {
+        pdf_value(&self.vertices, origin, direction)
+    }
+
+    fn random(&self, origin: Point3, sampler: &mut dyn Sampler) -> Vec3A {
+        sample(&self.vertices, sampler.get_2d()) - origin
+    }
+}
+
+pub(crate) fn bounding_box(vertices: &[Point3; 3]) -> AABB {
+    let min = vertices[0].min(vertices[1]).min(vertices[2]);
+    let max = vertices[0].max(vertices[1]).max(vertices[2]);
+    AABB::from_points(min, max).pad()
+}
+
+/// Where `r` hits the triangle, as the distance along it and the barycentric coordinates of the
+/// hit.
+///
+/// Uses the watertight test of Woop et al., "Watertight Ray/Triangle Intersection", so rays
+/// can't slip through between the triangles of a mesh that share an edge.
+pub(crate) fn intersect(r: &Ray, ray_t: Interval, vertices: &[Point3; 3]) -> Option<(f32, Vec3A)> {
+    // Move the ray to the origin and make its largest direction component Z.
+    let d = r.direction;
+    let a = d.abs();
+    let kz = if a.x > a.y && a.x > a.z {
+        0
+    } else if a.y > a.z {
+        1
+    } else {
+        2
+    };
+    let kx = (kz + 1) % 3;
+    let ky = (kx + 1) % 3;
+    let permute = |v: Vec3A| Vec3A::new(v[kx], v[ky], v[kz]);
+    let d = permute(d);
+    let [p0, p1, p2] = vertices.map(|v| permute(v - r.origin));
+
+    // Shear the triangle so the ray points down +Z.
+    let shear = Vec3A::new(-d.x / d.z, -d.y / d.z, 1.0 / d.z);
+    let [p0, p1, p2] =
+        [p0, p1, p2].map(|p| Vec3A::new(p.x + shear.x * p.z, p.y + shear.y * p.z, p.z * shear.z));
+
+    // Signed areas of the triangles the ray makes with each edge.
+    let edge = |a: Vec3A, b: Vec3A| {
+        let e = a.x * b.y - a.y * b.x;
+        if e == 0.0 {
+            // Too close to the edge for f32 to tell the side.
+            (a.x as f64 * b.y as f64 - a.y as f64 * b.x as f64) as f32
+        } else {
+            e
+        }
+    };
+    let e = Vec3A::new(edge(p1, p2), edge(p2, p0), edge(p0, p1));
+    if e.min_element() < 0.0 && e.max_element() > 0.0 {
+        return None;
+    }
+    let det = e.x + e.y + e.z;
+    if det == 0.0 {
+        return None;
+    }
+
+    let t = (e.x * p0.z + e.y * p1.z + e.z * p2.z) / det;
+    if !ray_t.contains(t) {
+        return None;
+    }
+    Some((t, e / det))
+}
+
+/// The record of a hit at `barycentric` on the triangle.
+pub(crate) fn hit_record<'a>(
+    r: &Ray,
+    t: f32,
+    barycentric: Vec3A,
+    vertices: &[Point3; 3],
+    normals: Option<[Vec3A; 3]>,
+    uvs: Option<[Vec2; 3]>,
+    material: &'a Material,
+) -> HitRecord<'a> {
+    let interpolate =
+        |v: [Vec3A; 3]| barycentric.x * v[0] + barycentric.y * v[1] + barycentric.z * v[2];
+    let p = interpolate(*vertices);
+    let (u, v) = match uvs {
+        Some(uvs) => {
+            let uv = barycentric.x * uvs[0] + barycentric.y * uvs[1] + barycentric.z * uvs[2];
+            (uv.x, uv.y)
+        }
+        None => (barycentric.y, barycentric.z),
+    };
+
+    let geometric = (vertices[1] - vertices[0])
+        .cross(vertices[2] - vertices[0])
+        .normalize();
+    let mut rec = HitRecord::new(r, t, p, u, v, material, geometric);
+    if let Some(normals) = normals {
+        // Shade with the interpolated normal, on the side of the surface the ray came from.
+        let shading = interpolate(normals).normalize_or_zero();
+        if shading != Vec3A::ZERO {
+            rec.normal = if shading.dot(rec.normal) < 0.0 {
+                -shading
+            } else {
+                shading
+            };
+        }
+    }
+    rec
+}
+
+/// Density, per unit solid angle, of [`sample`] picking `direction` from `origin`.
+pub(crate) fn pdf_value(vertices: &[Point3; 3], origin: Point3, direction: Vec3A) -> f32 {
+    let r = Ray::new(origin, direction);
+    let Some((t, _)) = intersect(&r, Interval::new(0.001, f32::INFINITY), vertices) else {
+        return 0.0;
+    };
+
+    let n = (vertices[1] - vertices[0]).cross(vertices[2] - vertices[0]);
+    let area = 0.5 * n.length();
+    let distance_squared = t * t * direction.length_squared();
+    let cosine = (direction.dot(n) / (direction.length() * n.length())).abs();
+    distance_squared / (cosine * area)
+}
+
+/// A point on the triangle for the random numbers `s`, spread evenly over its area.
+pub(crate) fn sample(vertices: &[Point3; 3], s: Vec2) -> Point3 {
+    let root = s.x.sqrt();
+    let (b1, b2) = (root * (1.0 - s.y), root * s.y);
+    (1.0 - root) * vertices[0] + b1 * vertices[1] + b2 * vertices[2]
+}
@@ -0,0 +1,360 @@
+//! glTF 2.0 files, as `.gltf` with their buffers and images or as binary `.glb`.
+//!
+//! The meshes of every node of the default scene are placed with the transforms of the nodes
+//! above them. Metallic-roughness materials become [`PhysicallyBased`] ones, and transmissive ones
+//! a [`Dielectric`]. Color images are decoded from sRGB and wrap the way their samplers say.
+
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use anyhow::{bail, Context, Result};
+use glam::{Mat4, Vec2, Vec3, Vec3A};
+use gltf::{
+    image::Format,
+    mesh::Mode,
+    texture::{self, WrappingMode},
+    Document, Node,
+};
+use image::Rgb32FImage;
+
+use crate::{
+    camera::Camera,
+    hittable::mesh::TriangleMesh,
+    material::{Dielectric, Material, PhysicallyBased},
+    texture::{ImageTexture, ScaledTexture, Texture, Wrap},
+    util::{
+        color::{Color, Transfer},
+        Point3,
+    },
+};
+
+use super::default_material;
+
+/// The meshes and cameras of a glTF file.
+pub struct GltfScene {
+    pub meshes: Vec<TriangleMesh>,
+    pub cameras: Vec<CameraSettings>,
+}
+
+/// Where a perspective camera of a glTF file is and what it sees.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraSettings {
+    /// Vertical field of view in degrees.
+    pub vfov: f32,
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vup: Vec3A,
+    pub aspect_ratio: Option<f32>,
+}
+
+impl CameraSettings {
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.vfov = self.vfov;
+        camera.lookfrom = self.lookfrom;
+        camera.lookat = self.lookat;
+        camera.vup = self.vup;
+        if let Some(aspect_ratio) = self.aspect_ratio {
+            camera.aspect_ratio = aspect_ratio;
+        }
+    }
+}
+
+/// Loads the meshes and cameras of the default scene of a glTF file, or of its first scene if it
+/// has no default.
+pub fn load(path: impl AsRef<Path>) -> Result<GltfScene> {
+    let path = path.as_ref();
+    let (document, buffers, images) =
+        gltf::import(path).with_context(|| format!("failed to load {}", path.display()))?;
+
+    let mut loader = Loader {
+        buffers: &buffers,
+        images: &images,
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        scene: GltfScene {
+            meshes: vec![],
+            cameras: vec![],
+        },
+    };
+    for node in scene_nodes(&document)? {
+        loader
+            .add_node(&node, Mat4::IDENTITY)
+            .with_context(|| format!("invalid node in {}", path.display()))?;
+    }
+    Ok(loader.scene)
+}
+
+/// Loads only the cameras of a glTF file, without reading its buffers and images.
+pub fn load_cameras(path: impl AsRef<Path>) -> Result<Vec<CameraSettings>> {
+    let path = path.as_ref();
+    let gltf =
+        gltf::Gltf::open(path).with_context(|| format!("failed to load {}", path.display()))?;
+
+    let mut cameras = vec![];
+    for node in scene_nodes(&gltf.document)? {
+        add_cameras(&node, Mat4::IDENTITY, &mut cameras);
+    }
+    Ok(cameras)
+}
+
+/// Adds the cameras of `node` and the nodes below it, in the order they are in the file.
+fn add_cameras(node: &Node, parent: Mat4, cameras: &mut Vec<CameraSettings>) {
+    let transform = parent * Mat4::from_cols_array_2d(&node.transform().matrix());
+    if let Some(camera) = node.camera() {
+        cameras.extend(camera_settings(&camera, transform));
+    }
+    for child in node.children() {
+        add_cameras(&child, transform, cameras);
+    }
+}
+
+fn scene_nodes(document: &Document) -> Result<impl Iterator<Item = Node<'_>>> {
+    let Some(scene) = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    else {
+        bail!("the file has no scenes");
+    };
+    Ok(scene.nodes())
+}
+
+struct Loader<'a> {
+    buffers: &'a [gltf::buffer::Data],
+    images: &'a [gltf::image::Data],
+    /// Images already converted, by their index in the file and how they were decoded.
+    textures: HashMap<(usize, Transfer), Arc<Rgb32FImage>>,
+    /// Materials already converted, by their index in the file.
+    materials: HashMap<usize, Material>,
+    scene: GltfScene,
+}
+
+impl Loader<'_> {
+    fn add_node(&mut self, node: &Node, parent: Mat4) -> Result<()> {
+        let transform = parent * Mat4::from_cols_array_2d(&node.transform().matrix());
+        if let Some(mesh) = node.mesh() {
+            for primitive in mesh.primitives() {
+                if let Some(mesh) = self.primitive(&primitive, transform)? {
+                    self.scene.meshes.push(mesh);
+                }
+            }
+        }
+        if let Some(camera) = node.camera() {
+            self.scene
+                .cameras
+                .extend(camera_settings(&camera, transform));
+        }
+        for child in node.children() {
+            self.add_node(&child, transform)?;
+        }
+        Ok(())
+    }
+
+    /// The triangles of a primitive, or `None` if it is made of points or lines.
+    fn primitive(
+        &mut self,
+        primitive: &gltf::Primitive,
+        transform: Mat4,
+    ) -> Result<Option<TriangleMesh>> {
+        let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()]));
+        let Some(positions) = reader.read_positions() else {
+            bail!("a primitive has no positions");
+        };
+        let positions = positions
+            .map(|p| Point3::from(transform.transform_point3(Vec3::from(p))))
+            .collect::<Vec<_>>();
+
+        let indices = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..positions.len() as u32).collect::<Vec<_>>(),
+        };
+        let faces = match primitive.mode() {
+            Mode::Triangles => indices
+                .chunks_exact(3)
+                .map(|face| [face[0], face[1], face[2]])
+                .collect(),
+            // Every other triangle of a strip winds the other way.
+            Mode::TriangleStrip => (2..indices.len())
+                .map(|i| match i % 2 {
+                    0 => [indices[i - 2], indices[i - 1], indices[i]],
+                    _ => [indices[i - 1], indices[i - 2], indices[i]],
+                })
+                .collect(),
+            Mode::TriangleFan => (2..indices.len())
+                .map(|i| [indices[0], indices[i - 1], indices[i]])
+                .collect(),
+            Mode::Points | Mode::Lines | Mode::LineLoop | Mode::LineStrip => return Ok(None),
+        };
+
+        let material = self.material(&primitive.material());
+        let mut mesh = TriangleMesh::new(positions, faces, material);
+        if let Some(normals) = reader.read_normals() {
+            // Normals are transformed by the inverse transpose to stay perpendicular to the
+            // surface when it is scaled unevenly.
+            let normal_transform = transform.inverse().transpose();
+            mesh.normals = normals
+                .map(|n| {
+                    let n = normal_transform.transform_vector3(Vec3::from(n));
+                    Vec3A::from(n).normalize_or_zero()
+                })
+                .collect();
+        }
+        if let Some(uvs) = reader.read_tex_coords(uv_set(&primitive.material())) {
+            // glTF puts the origin of images at the top left, textures at the bottom left.
+            mesh.uvs = uvs.into_f32().map(|[u, v]| Vec2::new(u, 1.0 - v)).collect();
+        }
+        mesh.validate()?;
+        Ok(Some(mesh))
+    }
+
+    fn material(&mut self, material: &gltf::Material) -> Material {
+        let Some(index) = material.index() else {
+            return default_material();
+        };
+        if let Some(material) = self.materials.get(&index) {
+            return material.clone();
+        }
+
+        let converted = self.convert_material(material);
+        self.materials.insert(index, converted.clone());
+        converted
+    }
+
+    fn convert_material(&mut self, material: &gltf::Material) -> Material {
+        if material
+            .transmission()
+            .is_some_and(|transmission| transmission.transmission_factor() > 0.5)
+        {
+            return Dielectric::new(material.ior().unwrap_or(1.5));
+        }
+
+        let pbr = material.pbr_metallic_roughness();
+        let [r, g, b, _] = pbr.base_color_factor();
+        let base_color = self.texture(
+            pbr.base_color_texture(),
+            Color::new(r, g, b),
+            Transfer::Srgb,
+        );
+        let metallic_roughness = self.texture(
+            pbr.metallic_roughness_texture(),
+            Color::new(1.0, pbr.roughness_factor(), pbr.metallic_factor()),
+            Transfer::Linear,
+        );
+        let strength = material.emissive_strength().unwrap_or(1.0);
+        let emission = self.texture(
+            material.emissive_texture(),
+            strength * Color::from(material.emissive_factor()),
+            Transfer::Srgb,
+        );
+        PhysicallyBased::new(base_color, metallic_roughness, emission)
+    }
+
+    /// The image of `info`, decoded with `transfer`, scaled by `factor`, or just `factor` if
+    /// there is no image.
+    fn texture(
+        &mut self,
+        info: Option<texture::Info>,
+        factor: Color,
+        transfer: Transfer,
+    ) -> Arc<dyn Texture> {
+        let Some(info) = info else {
+            return Arc::new(factor);
+        };
+        if factor == Color::ZERO {
+            return Arc::new(Color::ZERO);
+        }
+
+        let index = info.texture().source().index();
+        let image = self
+            .textures
+            .entry((index, transfer))
+            .or_insert_with(|| Arc::new(convert_image(&self.images[index], transfer)))
+            .clone();
+        let sampler = info.texture().sampler();
+        let image = Arc::new(ImageTexture::new(
+            image,
+            wrap(sampler.wrap_s()),
+            wrap(sampler.wrap_t()),
+        ));
+        if factor == Color::ONE {
+            image
+        } else {
+            Arc::new(ScaledTexture::new(image, factor))
+        }
+    }
+}
+
+/// The UV set the textures of `material` are looked up with. Meshes only keep one, so it is the
+/// one of the base color texture, or else of the first other texture there is.
+fn uv_set(material: &gltf::Material) -> u32 {
+    let pbr = material.pbr_metallic_roughness();
+    [
+        pbr.base_color_texture(),
+        material.emissive_texture(),
+        pbr.metallic_roughness_texture(),
+    ]
+    .into_iter()
+    .flatten()
+    .next()
+    .map_or(0, |info| info.tex_coord())
+}
+
+fn wrap(mode: WrappingMode) -> Wrap {
+    match mode {
+        WrappingMode::ClampToEdge => Wrap::Clamp,
+        WrappingMode::Repeat => Wrap::Repeat,
+        WrappingMode::MirroredRepeat => Wrap::MirroredRepeat,
+    }
+}
+
+/// Converts an image of any of the formats of glTF to linear RGB, decoding it with `transfer`.
+fn convert_image(data: &gltf::image::Data, transfer: Transfer) -> Rgb32FImage {
+    let (channels, bytes_per_channel) = match data.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+    let channel = |bytes: &[u8]| match bytes_per_channel {
+        1 => transfer.decode(bytes[0] as f32 / u8::MAX as f32),
+        2 => transfer.decode(u16::from_ne_bytes([bytes[0], bytes[1]]) as f32 / u16::MAX as f32),
+        // Float images are linear already.
+        _ => f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+    };
+
+    let pixel_bytes = channels * bytes_per_channel;
+    let rgb = data
+        .pixels
+        .chunks_exact(pixel_bytes)
+        .flat_map(|pixel| {
+            let c = |i: usize| channel(&pixel[i * bytes_per_channel..]);
+            match channels {
+                // Grayscale, with alpha after it if there are two channels.
+                1 | 2 => [c(0); 3],
+                _ => [c(0), c(1), c(2)],
+            }
+        })
+        .collect();
+    Rgb32FImage::from_raw(data.width, data.height, rgb)
+        .expect("glTF images have a pixel for every pixel of their size")
+}
+
+/// The settings of a perspective camera placed by `transform`, which looks down -Z with +Y up.
+fn camera_settings(camera: &gltf::Camera, transform: Mat4) -> Option<CameraSettings> {
+    let gltf::camera::Projection::Perspective(perspective) = camera.projection() else {
+        return None;
+    };
+    let lookfrom = transform.transform_point3(Vec3::ZERO);
+    let forward = transform.transform_vector3(Vec3::NEG_Z).normalize();
+    Some(CameraSettings {
+        vfov: perspective.yfov().to_degrees(),
+        lookfrom: lookfrom.into(),
+        lookat: (lookfrom + forward).into(),
+        vup: transform.transform_vector3(Vec3::Y).normalize().into(),
+        aspect_ratio: perspective.aspect_ratio(),
+    })
+}
@@ -0,0 +1,67 @@
+//! Loading of meshes and their materials from files made by other programs.
+
+use std::{path::Path, sync::Arc};
+
+use anyhow::{bail, Result};
+use image::{DynamicImage, Rgb32FImage};
+
+use crate::{
+    hittable::{hittable_list::HittableList, mesh::TriangleMesh},
+    material::{Lambertian, Material},
+    util::color::{Color, Transfer},
+};
+
+pub mod gltf;
+pub mod obj;
+pub mod ply;
+pub mod stl;
+
+/// Material of faces the file gives none for.
+pub fn default_material() -> Material {
+    Lambertian::new(Color::splat(0.8))
+}
+
+/// The colors of `image` made linear, decoding integer images with `transfer`.
+pub fn linear_image(image: &DynamicImage, transfer: Transfer) -> Rgb32FImage {
+    let mut linear = image.to_rgb32f();
+    // Float images are linear already.
+    if !matches!(
+        image,
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+    ) {
+        for c in linear.iter_mut() {
+            *c = transfer.decode(*c);
+        }
+    }
+    linear
+}
+
+/// Loads the meshes of a file, in a format picked from its extension.
+pub fn load_meshes(path: impl AsRef<Path>) -> Result<Vec<TriangleMesh>> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("obj") => obj::load(path),
+        Some("gltf" | "glb") => Ok(self::gltf::load(path)?.meshes),
+        Some("ply") => Ok(vec![ply::load(path)?]),
+        Some("stl") => Ok(vec![stl::load(path)?]),
+        _ => bail!(
+            "{} is not a mesh file, expected .obj, .gltf, .glb, .ply or .stl",
+            path.display()
+        ),
+    }
+}
+
+/// Loads the triangles of every mesh in a file, ready for [`crate::bvh::node::BVHNode::from_list`].
+pub fn load(path: impl AsRef<Path>) -> Result<HittableList> {
+    let mut list = HittableList::new();
+    for mesh in load_meshes(path)? {
+        for triangle in Arc::new(mesh).triangles().objects {
+            list.add_shared(triangle);
+        }
+    }
+    Ok(list)
+}
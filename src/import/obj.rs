@@ -0,0 +1,180 @@
+//! Wavefront OBJ files and the MTL material libraries they use.
+//!
+//! MTL materials become the closest of the materials there are:
+//!
+//! - emissive ones (`Ke`) a [`DiffuseLight`],
+//! - transparent ones (`d` below 1 or a refracting `illum`) a [`Dielectric`] with index `Ni`,
+//! - mirrors (`illum` 3, 5 or 8) a [`Metal`] of color `Ks`, as fuzzy as the specular exponent
+//!   `Ns` is low,
+//! - and everything else a [`Lambertian`] of color `Kd`, or the sRGB image `map_Kd` repeated
+//!   across the surface.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, Context, Result};
+use glam::Vec2;
+use image::Rgb32FImage;
+
+use crate::{
+    hittable::mesh::TriangleMesh,
+    material::{Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    texture::{ImageTexture, Wrap},
+    util::{
+        color::{Color, Transfer},
+        Point3,
+    },
+};
+
+use super::{default_material, linear_image};
+
+/// Loads a mesh for every group of faces in an OBJ file with the same material, triangulating
+/// polygons.
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<TriangleMesh>> {
+    let path = path.as_ref();
+    let options = tobj::LoadOptions {
+        single_index: true,
+        triangulate: true,
+        ignore_points: true,
+        ignore_lines: true,
+    };
+    let (models, materials) = tobj::load_obj(path, &options)
+        .with_context(|| format!("failed to load {}", path.display()))?;
+    let materials =
+        materials.with_context(|| format!("failed to load the materials of {}", path.display()))?;
+
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    let mut textures = Textures::default();
+    let materials = materials
+        .iter()
+        .map(|material| convert_material(material, base_dir, &mut textures))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut meshes = vec![];
+    for model in models {
+        let mesh = model.mesh;
+        if mesh.indices.is_empty() {
+            continue;
+        }
+
+        let vertices = mesh.positions.len() / 3;
+        let material = match mesh.material_id {
+            Some(id) => materials
+                .get(id)
+                .cloned()
+                .ok_or_else(|| anyhow!("`{}` uses missing material {id}", model.name))?,
+            None => default_material(),
+        };
+        let mut triangle_mesh = TriangleMesh::new(
+            mesh.positions
+                .chunks_exact(3)
+                .map(Point3::from_slice)
+                .collect(),
+            mesh.indices
+                .chunks_exact(3)
+                .map(|face| [face[0], face[1], face[2]])
+                .collect(),
+            material,
+        );
+        // Faces may leave out normals or UVs, which only makes sense to use if all of them
+        // have them.
+        if mesh.normals.len() == vertices * 3 {
+            triangle_mesh.normals = mesh
+                .normals
+                .chunks_exact(3)
+                .map(|n| Point3::from_slice(n).normalize_or_zero())
+                .collect();
+        }
+        if mesh.texcoords.len() == vertices * 2 {
+            triangle_mesh.uvs = mesh
+                .texcoords
+                .chunks_exact(2)
+                .map(Vec2::from_slice)
+                .collect();
+        }
+        triangle_mesh
+            .validate()
+            .with_context(|| format!("invalid mesh `{}` in {}", model.name, path.display()))?;
+        meshes.push(triangle_mesh);
+    }
+    Ok(meshes)
+}
+
+/// Images already loaded, as materials often share them.
+type Textures = HashMap<PathBuf, Arc<Rgb32FImage>>;
+
+fn convert_material(
+    material: &tobj::Material,
+    base_dir: &Path,
+    textures: &mut Textures,
+) -> Result<Material> {
+    let color = |c: Option<[f32; 3]>| c.map_or(Color::ZERO, Color::from);
+    let context = || format!("invalid material `{}`", material.name);
+
+    if let Some(emission) = material.unknown_param.get("Ke") {
+        let emission = parse_color(emission).with_context(context)?;
+        if emission.max_element() > 0.0 {
+            return Ok(DiffuseLight::new(emission));
+        }
+    }
+
+    let illumination = material.illumination_model.unwrap_or(2);
+    if material.dissolve.is_some_and(|d| d < 1.0) || matches!(illumination, 4 | 6 | 7 | 9) {
+        let ir = material.optical_density.filter(|&ir| ir >= 1.0);
+        return Ok(Dielectric::new(ir.unwrap_or(1.5)));
+    }
+
+    let diffuse = material.diffuse.map_or(Color::splat(0.8), Color::from);
+    let specular = color(material.specular);
+    if matches!(illumination, 3 | 5 | 8) {
+        // Roughness from the Phong exponent as in Walter et al., "Microfacet Models for Refraction
+        // through Rough Surfaces". Without one the surface is a sharp mirror.
+        let fuzz = material
+            .shininess
+            .map_or(0.0, |shininess| (2.0 / (shininess.max(0.0) + 2.0)).sqrt());
+        let albedo = if specular.max_element() > 0.0 {
+            specular
+        } else {
+            diffuse
+        };
+        return Ok(Metal::new(albedo, fuzz));
+    }
+
+    match &material.diffuse_texture {
+        Some(texture) => {
+            let path = base_dir.join(texture.replace('\\', "/"));
+            let image = match textures.get(&path) {
+                Some(image) => image.clone(),
+                None => {
+                    let image = image::open(&path)
+                        .with_context(|| format!("failed to open image {}", path.display()))
+                        .with_context(context)?;
+                    let image = Arc::new(linear_image(&image, Transfer::Srgb));
+                    textures.insert(path, image.clone());
+                    image
+                }
+            };
+            Ok(Lambertian::new(ImageTexture::new(
+                image,
+                Wrap::Repeat,
+                Wrap::Repeat,
+            )))
+        }
+        None => Ok(Lambertian::new(diffuse)),
+    }
+}
+
+fn parse_color(s: &str) -> Result<Color> {
+    let components = s
+        .split_whitespace()
+        .map(|c| c.parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()?;
+    match components[..] {
+        [x] => Ok(Color::splat(x)),
+        [x, y, z] => Ok(Color::new(x, y, z)),
+        _ => Err(anyhow!("expected one or three numbers, got `{s}`")),
+    }
+}
@@ -0,0 +1,426 @@
+//! Stanford PLY files, in ASCII or in binary of either byte order.
+//!
+//! Vertices have positions `x`, `y` and `z`, and may have normals `nx`, `ny` and `nz`, UVs `u`
+//! and `v` (or `s` and `t`) and colors `red`, `green` and `blue`. Faces list the indices of their
+//! vertices as `vertex_indices` or `vertex_index`, and polygons are split into triangles. Other
+//! elements and properties are skipped.
+//!
+//! PLY files have no materials. A mesh with vertex colors gets a [`Lambertian`] that blends them
+//! across its faces with [`VertexColors`], anything else the default material. Colors stored as
+//! integers are taken to be sRGB, like those of images.
+
+use std::{fs, path::Path, str::FromStr, str::SplitAsciiWhitespace, sync::Arc};
+
+use anyhow::{anyhow, bail, Context, Result};
+use glam::{Vec2, Vec3A};
+
+use crate::{
+    hittable::mesh::TriangleMesh,
+    material::Lambertian,
+    texture::VertexColors,
+    util::{
+        color::{Color, Transfer},
+        Point3,
+    },
+};
+
+use super::default_material;
+
+/// Loads the mesh of a PLY file.
+pub fn load(path: impl AsRef<Path>) -> Result<TriangleMesh> {
+    let path = path.as_ref();
+    let data = fs::read(path).with_context(|| format!("failed to load {}", path.display()))?;
+    parse(&data).with_context(|| format!("invalid PLY file {}", path.display()))
+}
+
+fn parse(data: &[u8]) -> Result<TriangleMesh> {
+    let (format, elements, body) = parse_header(data)?;
+    let mut body = match format {
+        Format::Ascii => Body::Ascii(
+            std::str::from_utf8(body)
+                .context("ASCII data is not text")?
+                .split_ascii_whitespace(),
+        ),
+        Format::BinaryLittleEndian => Body::Binary {
+            data: body,
+            big_endian: false,
+        },
+        Format::BinaryBigEndian => Body::Binary {
+            data: body,
+            big_endian: true,
+        },
+    };
+
+    let mut vertices = Vertices::default();
+    let mut faces = vec![];
+    let mut row = vec![];
+    let mut list = vec![];
+    for element in &elements {
+        let context = || format!("invalid {} element", element.name);
+        match element.name.as_str() {
+            "vertex" => {
+                let properties = VertexProperties::find(element).with_context(context)?;
+                for _ in 0..element.count {
+                    body.read_row(element, None, &mut row, &mut list)
+                        .with_context(context)?;
+                    vertices.push(&properties, &row);
+                }
+            }
+            "face" => {
+                let Some(indices) = element
+                    .position(|name| matches!(name, "vertex_indices" | "vertex_index"))
+                    .filter(|&i| element.properties[i].kind.is_list())
+                else {
+                    bail!("faces have no `vertex_indices` list");
+                };
+                for _ in 0..element.count {
+                    body.read_row(element, Some(indices), &mut row, &mut list)
+                        .with_context(context)?;
+                    // Split polygons into a fan of triangles around their first vertex.
+                    for i in 2..list.len() {
+                        faces.push([list[0], list[i - 1], list[i]].map(|i| i as u32));
+                    }
+                }
+            }
+            _ => {
+                for _ in 0..element.count {
+                    body.read_row(element, None, &mut row, &mut list)
+                        .with_context(context)?;
+                }
+            }
+        }
+    }
+
+    let colors: Arc<[Color]> = vertices.colors.into();
+    let material = if colors.is_empty() {
+        default_material()
+    } else {
+        Lambertian::new(VertexColors::new(colors.clone()))
+    };
+    let mut mesh = TriangleMesh::new(vertices.positions, faces, material);
+    mesh.normals = vertices.normals;
+    mesh.uvs = vertices.uvs;
+    mesh.colors = colors;
+    mesh.validate()?;
+    Ok(mesh)
+}
+
+/// Where the properties of vertices are in their rows.
+struct VertexProperties {
+    position: [usize; 3],
+    normal: Option<[usize; 3]>,
+    uv: Option<[usize; 2]>,
+    color: Option<[usize; 3]>,
+    /// The value of a color component at full intensity.
+    full_intensity: f32,
+    /// How the color components are encoded.
+    transfer: Transfer,
+}
+
+impl VertexProperties {
+    fn find(element: &Element) -> Result<Self> {
+        let Some(position) = find_scalars(element, ["x", "y", "z"]) else {
+            bail!("vertices have no `x`, `y` and `z`");
+        };
+        let color = find_scalars(element, ["red", "green", "blue"]);
+        let color_type = color.map(|[red, ..]| match element.properties[red].kind {
+            PropertyKind::Scalar(scalar) => scalar,
+            PropertyKind::List { .. } => unreachable!("colors are scalars"),
+        });
+        let full_intensity = color_type.map_or(1.0, Scalar::full_intensity);
+        // Floating point colors are linear already.
+        let transfer = match color_type {
+            Some(Scalar::F32 | Scalar::F64) | None => Transfer::Linear,
+            Some(_) => Transfer::Srgb,
+        };
+        Ok(Self {
+            position,
+            normal: find_scalars(element, ["nx", "ny", "nz"]),
+            uv: find_scalars(element, ["u", "v"]).or_else(|| find_scalars(element, ["s", "t"])),
+            color,
+            full_intensity,
+            transfer,
+        })
+    }
+}
+
+/// Where the scalar properties `names` are in the rows of `element`, if it has all of them.
+fn find_scalars<const N: usize>(element: &Element, names: [&str; N]) -> Option<[usize; N]> {
+    let mut indices = [0; N];
+    for (index, name) in indices.iter_mut().zip(names) {
+        *index = element
+            .position(|n| n == name)
+            .filter(|&i| !element.properties[i].kind.is_list())?;
+    }
+    Some(indices)
+}
+
+#[derive(Default)]
+struct Vertices {
+    positions: Vec<Point3>,
+    normals: Vec<Vec3A>,
+    uvs: Vec<Vec2>,
+    colors: Vec<Color>,
+}
+
+impl Vertices {
+    fn push(&mut self, properties: &VertexProperties, row: &[f64]) {
+        let vector =
+            |[x, y, z]: [usize; 3]| Point3::new(row[x] as f32, row[y] as f32, row[z] as f32);
+        self.positions.push(vector(properties.position));
+        if let Some(normal) = properties.normal {
+            self.normals.push(vector(normal).normalize_or_zero());
+        }
+        if let Some([u, v]) = properties.uv {
+            self.uvs.push(Vec2::new(row[u] as f32, row[v] as f32));
+        }
+        if let Some(color) = properties.color {
+            let color = vector(color) / properties.full_intensity;
+            let decode = |c: f32| properties.transfer.decode(c);
+            self.colors.push(Color::new(
+                decode(color.x),
+                decode(color.y),
+                decode(color.z),
+            ));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl FromStr for Scalar {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "char" | "int8" => Ok(Self::I8),
+            "uchar" | "uint8" => Ok(Self::U8),
+            "short" | "int16" => Ok(Self::I16),
+            "ushort" | "uint16" => Ok(Self::U16),
+            "int" | "int32" => Ok(Self::I32),
+            "uint" | "uint32" => Ok(Self::U32),
+            "float" | "float32" => Ok(Self::F32),
+            "double" | "float64" => Ok(Self::F64),
+            _ => Err(anyhow!("unknown property type `{s}`")),
+        }
+    }
+}
+
+impl Scalar {
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+
+    /// The value that stands for full intensity in a color of this type.
+    fn full_intensity(self) -> f32 {
+        match self {
+            Self::I8 => i8::MAX as f32,
+            Self::U8 => u8::MAX as f32,
+            Self::I16 => i16::MAX as f32,
+            Self::U16 => u16::MAX as f32,
+            Self::I32 => i32::MAX as f32,
+            Self::U32 => u32::MAX as f32,
+            Self::F32 | Self::F64 => 1.0,
+        }
+    }
+
+    fn decode(self, bytes: &[u8], big_endian: bool) -> f64 {
+        macro_rules! decode {
+            ($t:ty) => {{
+                let bytes = bytes.try_into().expect("bytes are the size of the type");
+                let value = if big_endian {
+                    <$t>::from_be_bytes(bytes)
+                } else {
+                    <$t>::from_le_bytes(bytes)
+                };
+                value as f64
+            }};
+        }
+        match self {
+            Self::I8 => decode!(i8),
+            Self::U8 => decode!(u8),
+            Self::I16 => decode!(i16),
+            Self::U16 => decode!(u16),
+            Self::I32 => decode!(i32),
+            Self::U32 => decode!(u32),
+            Self::F32 => decode!(f32),
+            Self::F64 => decode!(f64),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PropertyKind {
+    Scalar(Scalar),
+    /// A number of items followed by the items.
+    List {
+        count: Scalar,
+        item: Scalar,
+    },
+}
+
+impl PropertyKind {
+    fn is_list(self) -> bool {
+        matches!(self, Self::List { .. })
+    }
+}
+
+#[derive(Debug)]
+struct Property {
+    name: String,
+    kind: PropertyKind,
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn position(&self, matches: impl Fn(&str) -> bool) -> Option<usize> {
+        self.properties.iter().position(|p| matches(&p.name))
+    }
+}
+
+/// The format and elements the header of a file declares, and the data after it.
+fn parse_header(data: &[u8]) -> Result<(Format, Vec<Element>, &[u8])> {
+    if !data.starts_with(b"ply") {
+        bail!("the file doesn't start with `ply`");
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+    let mut rest = data;
+    loop {
+        let Some(end) = rest.iter().position(|&b| b == b'\n') else {
+            bail!("the header has no `end_header`");
+        };
+        let line = std::str::from_utf8(&rest[..end])
+            .context("the header is not text")?
+            .trim();
+        rest = &rest[end + 1..];
+
+        let context = || format!("invalid header line `{line}`");
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        match words[..] {
+            ["ply"] | [] | ["comment", ..] | ["obj_info", ..] => {}
+            ["format", name, _version] => {
+                format = Some(match name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => bail!("unknown format `{name}`"),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_owned(),
+                count: count.parse().with_context(context)?,
+                properties: vec![],
+            }),
+            ["property", ref kind @ .., name] => {
+                let kind = match kind {
+                    [scalar] => PropertyKind::Scalar(scalar.parse().with_context(context)?),
+                    ["list", count, item] => PropertyKind::List {
+                        count: count.parse().with_context(context)?,
+                        item: item.parse().with_context(context)?,
+                    },
+                    _ => return Err(anyhow!("expected a type and a name")).with_context(context),
+                };
+                let Some(element) = elements.last_mut() else {
+                    bail!("property `{name}` comes before any element");
+                };
+                element.properties.push(Property {
+                    name: name.to_owned(),
+                    kind,
+                });
+            }
+            ["end_header"] => break,
+            _ => return Err(anyhow!("unknown header line `{line}`")),
+        }
+    }
+
+    let format = format.ok_or_else(|| anyhow!("the header has no format"))?;
+    Ok((format, elements, rest))
+}
+
+/// The data of the elements, read one number at a time.
+enum Body<'a> {
+    Ascii(SplitAsciiWhitespace<'a>),
+    Binary { data: &'a [u8], big_endian: bool },
+}
+
+impl Body<'_> {
+    fn read(&mut self, scalar: Scalar) -> Result<f64> {
+        match self {
+            Body::Ascii(words) => {
+                let word = words.next().context("unexpected end of file")?;
+                word.parse()
+                    .with_context(|| format!("expected a number, got `{word}`"))
+            }
+            Body::Binary { data, big_endian } => {
+                if data.len() < scalar.size() {
+                    bail!("unexpected end of file");
+                }
+                let (bytes, rest) = data.split_at(scalar.size());
+                *data = rest;
+                Ok(scalar.decode(bytes, *big_endian))
+            }
+        }
+    }
+
+    /// Reads a row of `element` into `row`, a number for every property and zero for lists,
+    /// and the items of the list property at `keep` into `list`.
+    fn read_row(
+        &mut self,
+        element: &Element,
+        keep: Option<usize>,
+        row: &mut Vec<f64>,
+        list: &mut Vec<f64>,
+    ) -> Result<()> {
+        row.clear();
+        list.clear();
+        for (i, property) in element.properties.iter().enumerate() {
+            match property.kind {
+                PropertyKind::Scalar(scalar) => row.push(self.read(scalar)?),
+                PropertyKind::List { count, item } => {
+                    row.push(0.0);
+                    let count = self.read(count)?;
+                    if count < 0.0 {
+                        bail!("list `{}` has {count} items", property.name);
+                    }
+                    for _ in 0..count as usize {
+                        let item = self.read(item)?;
+                        if keep == Some(i) {
+                            list.push(item);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+//! STL files, as made by CAD programs, in binary or ASCII.
+//!
+//! STL files are only a list of triangles, without shared vertices, smooth normals or materials,
+//! so they become a flat shaded mesh of the default material.
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{hittable::mesh::TriangleMesh, util::Point3};
+
+use super::default_material;
+
+/// Size of the header of binary files, and of the number of triangles after it.
+const HEADER_SIZE: usize = 80 + 4;
+/// Size of a triangle in binary files: a normal, three vertices and two bytes of attributes.
+const TRIANGLE_SIZE: usize = 12 * 4 + 2;
+
+/// Loads the triangles of an STL file.
+pub fn load(path: impl AsRef<Path>) -> Result<TriangleMesh> {
+    let path = path.as_ref();
+    let data = fs::read(path).with_context(|| format!("failed to load {}", path.display()))?;
+    let positions = if is_binary(&data) {
+        parse_binary(&data)
+    } else {
+        parse_ascii(&data)
+    }
+    .with_context(|| format!("invalid STL file {}", path.display()))?;
+
+    let indices = (0..positions.len() as u32 / 3)
+        .map(|i| [3 * i, 3 * i + 1, 3 * i + 2])
+        .collect();
+    Ok(TriangleMesh::new(positions, indices, default_material()))
+}
+
+/// Binary files may start with `solid` like ASCII ones do, but their size always matches the
+/// number of triangles they give.
+fn is_binary(data: &[u8]) -> bool {
+    !data.starts_with(b"solid") || binary_size(data) == Some(data.len())
+}
+
+/// The size of a binary file with the number of triangles in its header.
+fn binary_size(data: &[u8]) -> Option<usize> {
+    let count = data.get(80..HEADER_SIZE)?;
+    let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+    Some(HEADER_SIZE + count * TRIANGLE_SIZE)
+}
+
+fn parse_binary(data: &[u8]) -> Result<Vec<Point3>> {
+    let Some(size) = binary_size(data) else {
+        bail!("the file is too short for its header");
+    };
+    if data.len() < size {
+        bail!(
+            "the file is {} bytes, too short for its {} triangles",
+            data.len(),
+            (size - HEADER_SIZE) / TRIANGLE_SIZE
+        );
+    }
+
+    let float = |bytes: &[u8]| f32::from_le_bytes(bytes.try_into().unwrap());
+    Ok(data[HEADER_SIZE..size]
+        .chunks_exact(TRIANGLE_SIZE)
+        .flat_map(|triangle| {
+            // Skip the normal, the vertices wind counterclockwise around it anyway.
+            triangle[12..48].chunks_exact(12).map(|vertex| {
+                Point3::new(
+                    float(&vertex[0..4]),
+                    float(&vertex[4..8]),
+                    float(&vertex[8..12]),
+                )
+            })
+        })
+        .collect())
+}
+
+fn parse_ascii(data: &[u8]) -> Result<Vec<Point3>> {
+    let text = std::str::from_utf8(data).context("the file is neither binary STL nor text")?;
+    let mut words = text.split_ascii_whitespace();
+    let mut positions = vec![];
+    while let Some(word) = words.next() {
+        if word != "vertex" {
+            continue;
+        }
+        let mut coordinate = || -> Result<f32> {
+            let word = words.next().context("unexpected end of file")?;
+            word.parse()
+                .with_context(|| format!("expected a number, got `{word}`"))
+        };
+        positions.push(Point3::new(coordinate()?, coordinate()?, coordinate()?));
+    }
+    if positions.len() % 3 != 0 {
+        bail!("facets must have 3 vertices");
+    }
+    Ok(positions)
+}
@@ -0,0 +1,494 @@
+//! Ways of working out how much light arrives along a camera ray.
+
+use std::str::FromStr;
+
+use anyhow::{bail, Error};
+
+use crate::{
+    hittable::{hittable_list::HittableList, HitRecord, Hittable},
+    material::{MaterialT, ScatterRecord},
+    sampler::Sampler,
+    util::{
+        color::Color,
+        interval::Interval,
+        pdf::{CosinePDF, ScatterPDF, PDF},
+        ray::Ray,
+        Point3,
+    },
+};
+
+/// What an integrator renders.
+pub struct World<'a> {
+    pub objects: &'a dyn Hittable,
+    /// Emissive objects that are sampled directly.
+    pub lights: &'a HittableList,
+    /// Light arriving from rays that hit nothing.
+    pub background: Color,
+}
+
+impl World<'_> {
+    /// The first hit of `r`.
+    pub fn hit(&self, r: &Ray) -> Option<HitRecord<'_>> {
+        self.objects.hit(r, Interval::new(0.001, f32::INFINITY))
+    }
+}
+
+pub trait Integrator: Sync {
+    /// Light arriving along the camera ray `r`.
+    fn ray_color(&self, r: &Ray, world: &World, sampler: &mut dyn Sampler) -> Color;
+}
+
+/// The integrators a render can use.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum IntegratorKind {
+    /// Path tracing that only follows the materials, as in the books.
+    Naive,
+    /// Path tracing with light sampling and Russian roulette.
+    #[default]
+    Path,
+    /// The recursive reference for [`IntegratorKind::Path`], cut off at the maximum depth
+    /// instead of by Russian roulette.
+    PathRecursive,
+    /// How much of the hemisphere above the first hit is open.
+    AmbientOcclusion,
+    /// Only light that reaches the first diffuse hit directly.
+    DirectLighting,
+    /// Shading normals of the first hit, mapped to `[0, 1]`.
+    Normals,
+    /// Texture coordinates of the first hit.
+    Uv,
+    /// Distance to the first hit.
+    Depth,
+    /// Number of bounding boxes and objects a camera ray is tested against, as a heat map.
+    BvhCost,
+}
+
+/// Bounces after which [`PathIntegrator`] gives up on a path that Russian roulette hasn't ended,
+/// only so paths between perfect mirrors can't go on forever.
+const MAX_PATH_BOUNCES: i32 = 1024;
+
+/// The settings integrators are created from.
+#[derive(Debug, Clone, Copy)]
+pub struct IntegratorSettings {
+    /// Bounces after which paths are cut off, by every integrator but [`PathIntegrator`].
+    pub max_depth: i32,
+    pub roulette_depth: i32,
+    /// How far away occluders count for ambient occlusion.
+    pub ao_distance: f32,
+}
+
+impl IntegratorKind {
+    pub fn create(self, settings: &IntegratorSettings) -> Box<dyn Integrator> {
+        let max_depth = settings.max_depth;
+        match self {
+            IntegratorKind::Naive => Box::new(NaiveIntegrator { max_depth }),
+            IntegratorKind::Path => Box::new(PathIntegrator {
+                roulette_depth: settings.roulette_depth,
+            }),
+            IntegratorKind::PathRecursive => Box::new(RecursivePathIntegrator { max_depth }),
+            IntegratorKind::AmbientOcclusion => Box::new(AmbientOcclusionIntegrator {
+                distance: settings.ao_distance,
+            }),
+            IntegratorKind::DirectLighting => Box::new(DirectLightingIntegrator { max_depth }),
+            IntegratorKind::Normals => Box::new(DebugIntegrator::Normals),
+            IntegratorKind::Uv => Box::new(DebugIntegrator::Uv),
+            IntegratorKind::Depth => Box::new(DebugIntegrator::Depth),
+            IntegratorKind::BvhCost => Box::new(DebugIntegrator::BvhCost),
+        }
+    }
+}
+
+impl FromStr for IntegratorKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "naive" => IntegratorKind::Naive,
+            "path" => IntegratorKind::Path,
+            "path-recursive" => IntegratorKind::PathRecursive,
+            "ao" => IntegratorKind::AmbientOcclusion,
+            "direct" => IntegratorKind::DirectLighting,
+            "normals" => IntegratorKind::Normals,
+            "uv" => IntegratorKind::Uv,
+            "depth" => IntegratorKind::Depth,
+            "bvh-cost" => IntegratorKind::BvhCost,
+            _ => bail!(
+                "unknown integrator `{s}`, expected naive, path, path-recursive, ao, direct, \
+                 normals, uv, depth or bvh-cost"
+            ),
+        })
+    }
+}
+
+/// Follows scattered rays until they happen to hit a light.
+pub struct NaiveIntegrator {
+    pub max_depth: i32,
+}
+
+impl NaiveIntegrator {
+    fn path_color(&self, r: &Ray, world: &World, depth: i32, sampler: &mut dyn Sampler) -> Color {
+        if depth <= 0 {
+            return Color::ZERO;
+        }
+
+        let Some(rec) = world.hit(r) else {
+            return world.background;
+        };
+
+        let color_from_emission = rec.material.emitted(rec.u, rec.v, rec.p);
+
+        let color_from_scatter = match rec.material.scatter(r, &rec, sampler) {
+            None => Color::ZERO,
+            Some(ScatterRecord::Specular { attenuation, ray }) => {
+                attenuation * self.path_color(&ray, world, depth - 1, sampler)
+            }
+            Some(ScatterRecord::Pdf { attenuation, pdf }) => {
+                let scattered = Ray::new_with_time(rec.p, pdf.generate(sampler), r.time);
+                let pdf_value = pdf.value(scattered.direction);
+                if pdf_value <= 0.0 {
+                    return color_from_emission;
+                }
+                let scattering_pdf = rec.material.scattering_pdf(r, &rec, &scattered);
+                let incoming = self.path_color(&scattered, world, depth - 1, sampler);
+                attenuation * scattering_pdf * incoming / pdf_value
+            }
+        };
+
+        color_from_emission + color_from_scatter
+    }
+}
+
+impl Integrator for NaiveIntegrator {
+    fn ray_color(&self, r: &Ray, world: &World, sampler: &mut dyn Sampler) -> Color {
+        self.path_color(r, world, self.max_depth, sampler)
+    }
+}
+
+/// Follows paths in a loop, sampling the lights directly on every diffuse bounce.
+///
+/// Every diffuse bounce samples a point on one of the lights with a shadow ray, as well as a
+/// scattered ray from the material. Lights reached either way are weighted with the power
+/// heuristic, so each is counted once and by whichever strategy suits it best.
+///
+/// After `roulette_depth` bounces, paths are randomly ended with a chance that grows as their
+/// throughput drops, and the ones that continue are weighted up to make up for it. That is the
+/// only way paths end short of escaping or being absorbed, so unlike a fixed depth it leaves out
+/// no light on average.
+pub struct PathIntegrator {
+    pub roulette_depth: i32,
+}
+
+impl Integrator for PathIntegrator {
+    fn ray_color(&self, r: &Ray, world: &World, sampler: &mut dyn Sampler) -> Color {
+        let mut color = Color::ZERO;
+        let mut throughput = Color::ONE;
+        let mut ray = r.clone();
+        let mut light_sampled_from = None;
+
+        for bounce in 0..MAX_PATH_BOUNCES {
+            let Some(rec) = world.hit(&ray) else {
+                color += throughput * world.background;
+                break;
+            };
+
+            color += throughput * emitted(&ray, &rec, world, light_sampled_from);
+
+            match rec.material.scatter(&ray, &rec, sampler) {
+                None => break,
+                Some(ScatterRecord::Specular {
+                    attenuation,
+                    ray: scattered,
+                }) => {
+                    throughput *= attenuation;
+                    ray = scattered;
+                    light_sampled_from = None;
+                }
+                Some(ScatterRecord::Pdf { attenuation, pdf }) => {
+                    let sample_lights = !world.lights.is_empty() && bounce + 1 < MAX_PATH_BOUNCES;
+                    if sample_lights {
+                        color += throughput
+                            * attenuation
+                            * sample_light(&ray, &rec, &pdf, world, sampler);
+                    }
+
+                    let scattered = Ray::new_with_time(rec.p, pdf.generate(sampler), ray.time);
+                    let pdf_value = pdf.value(scattered.direction);
+                    if pdf_value <= 0.0 {
+                        break;
+                    }
+                    let scattering_pdf = rec.material.scattering_pdf(&ray, &rec, &scattered);
+
+                    throughput *= attenuation * scattering_pdf / pdf_value;
+                    ray = scattered;
+                    light_sampled_from = sample_lights.then_some((rec.p, pdf_value));
+                }
+            }
+
+            if bounce + 1 >= self.roulette_depth {
+                let survival = throughput.max_element().min(0.95);
+                if survival <= 0.0 || sampler.get_1d() >= survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+        }
+
+        color
+    }
+}
+
+/// The same light transport as [`PathIntegrator`] by recursion, with paths cut off after
+/// `max_depth` bounces instead of by Russian roulette.
+pub struct RecursivePathIntegrator {
+    pub max_depth: i32,
+}
+
+impl RecursivePathIntegrator {
+    /// `light_sampled_from` is the point `r` was scattered from and the density it was scattered
+    /// with, when that bounce also sampled the lights.
+    fn path_color(
+        &self,
+        r: &Ray,
+        world: &World,
+        depth: i32,
+        sampler: &mut dyn Sampler,
+        light_sampled_from: Option<(Point3, f32)>,
+    ) -> Color {
+        if depth <= 0 {
+            return Color::ZERO;
+        }
+
+        let Some(rec) = world.hit(r) else {
+            return world.background;
+        };
+
+        let mut color = emitted(r, &rec, world, light_sampled_from);
+
+        let (attenuation, material_pdf) = match rec.material.scatter(r, &rec, sampler) {
+            None => return color,
+            Some(ScatterRecord::Specular { attenuation, ray }) => {
+                let incoming = self.path_color(&ray, world, depth - 1, sampler, None);
+                return color + attenuation * incoming;
+            }
+            Some(ScatterRecord::Pdf { attenuation, pdf }) => (attenuation, pdf),
+        };
+
+        // Lights sampled from here can only also be reached by the scattered ray if it has a
+        // bounce left to do so.
+        let sample_lights = !world.lights.is_empty() && depth > 1;
+        if sample_lights {
+            color += attenuation * sample_light(r, &rec, &material_pdf, world, sampler);
+        }
+
+        let scattered = Ray::new_with_time(rec.p, material_pdf.generate(sampler), r.time);
+        let pdf_value = material_pdf.value(scattered.direction);
+        if pdf_value <= 0.0 {
+            return color;
+        }
+        let scattering_pdf = rec.material.scattering_pdf(r, &rec, &scattered);
+
+        let light_sampled_from = sample_lights.then_some((rec.p, pdf_value));
+        let incoming = self.path_color(&scattered, world, depth - 1, sampler, light_sampled_from);
+        color + attenuation * scattering_pdf * incoming / pdf_value
+    }
+}
+
+impl Integrator for RecursivePathIntegrator {
+    fn ray_color(&self, r: &Ray, world: &World, sampler: &mut dyn Sampler) -> Color {
+        self.path_color(r, world, self.max_depth, sampler, None)
+    }
+}
+
+/// White where nothing is within `distance` of the first hit, darker the more of the
+/// hemisphere above it is blocked.
+pub struct AmbientOcclusionIntegrator {
+    pub distance: f32,
+}
+
+impl Integrator for AmbientOcclusionIntegrator {
+    fn ray_color(&self, r: &Ray, world: &World, sampler: &mut dyn Sampler) -> Color {
+        let Some(rec) = world.hit(r) else {
+            return Color::ONE;
+        };
+
+        // Cosine weighted directions make the fraction of open ones the occlusion estimate.
+        let direction = CosinePDF::new(&rec.normal).generate(sampler);
+        let occluder = world.objects.hit(
+            &Ray::new_with_time(rec.p, direction.normalize(), r.time),
+            Interval::new(0.001, self.distance),
+        );
+        if occluder.is_some() {
+            Color::ZERO
+        } else {
+            Color::ONE
+        }
+    }
+}
+
+/// Emission, and light arriving directly from the lights, at the first diffuse hit.
+///
+/// Mirrors and glass are followed for up to `max_depth` bounces to find it.
+pub struct DirectLightingIntegrator {
+    pub max_depth: i32,
+}
+
+impl Integrator for DirectLightingIntegrator {
+    fn ray_color(&self, r: &Ray, world: &World, sampler: &mut dyn Sampler) -> Color {
+        let mut throughput = Color::ONE;
+        let mut ray = r.clone();
+
+        for _ in 0..self.max_depth {
+            let Some(rec) = world.hit(&ray) else {
+                return throughput * world.background;
+            };
+
+            let emitted = rec.material.emitted(rec.u, rec.v, rec.p);
+            match rec.material.scatter(&ray, &rec, sampler) {
+                None => return throughput * emitted,
+                Some(ScatterRecord::Specular {
+                    attenuation,
+                    ray: scattered,
+                }) => {
+                    throughput *= attenuation;
+                    ray = scattered;
+                }
+                Some(ScatterRecord::Pdf { attenuation, .. }) => {
+                    let direct = if world.lights.is_empty() {
+                        Color::ZERO
+                    } else {
+                        sample_light_only(&ray, &rec, world, sampler)
+                    };
+                    return throughput * (emitted + attenuation * direct);
+                }
+            }
+        }
+
+        Color::ZERO
+    }
+}
+
+/// Views of the first hit for debugging scenes.
+pub enum DebugIntegrator {
+    Normals,
+    Uv,
+    Depth,
+    BvhCost,
+}
+
+impl DebugIntegrator {
+    /// Traversal cost shown as full red in [`DebugIntegrator::BvhCost`].
+    const MAX_COST: f32 = 200.0;
+}
+
+impl Integrator for DebugIntegrator {
+    fn ray_color(&self, r: &Ray, world: &World, _sampler: &mut dyn Sampler) -> Color {
+        if let DebugIntegrator::BvhCost = self {
+            let cost = world
+                .objects
+                .traversal_cost(r, Interval::new(0.001, f32::INFINITY));
+            return heat_map(cost as f32 / Self::MAX_COST);
+        }
+
+        let Some(rec) = world.hit(r) else {
+            return Color::ZERO;
+        };
+        match self {
+            DebugIntegrator::Normals => 0.5 * (rec.normal + Color::ONE),
+            DebugIntegrator::Uv => Color::new(rec.u, rec.v, 0.0),
+            DebugIntegrator::Depth => Color::splat(rec.t * r.direction.length()),
+            DebugIntegrator::BvhCost => unreachable!(),
+        }
+    }
+}
+
+/// Blue through green to red as `t` goes from 0 to 1.
+fn heat_map(t: f32) -> Color {
+    let t = t.clamp(0.0, 1.0);
+    Color::new(
+        (2.0 * t - 1.0).max(0.0),
+        1.0 - (2.0 * t - 1.0).abs(),
+        (1.0 - 2.0 * t).max(0.0),
+    )
+}
+
+/// Light emitted at `rec` towards where `r` came from.
+///
+/// `light_sampled_from` is the point `r` was scattered from and the density it was scattered
+/// with, when that bounce also sampled the lights. The emission is then weighted against the
+/// chance of the light sample having found it.
+fn emitted(
+    r: &Ray,
+    rec: &HitRecord,
+    world: &World,
+    light_sampled_from: Option<(Point3, f32)>,
+) -> Color {
+    let emitted = rec.material.emitted(rec.u, rec.v, rec.p);
+    match light_sampled_from {
+        Some((origin, scatter_pdf)) if emitted != Color::ZERO => {
+            let light_pdf = world.lights.pdf_value(origin, r.direction);
+            emitted * power_heuristic(scatter_pdf, light_pdf)
+        }
+        _ => emitted,
+    }
+}
+
+/// Light reaching `rec` directly from a random point on one of the lights, before the material's
+/// attenuation and weighted against scattering with `material_pdf` having found it.
+fn sample_light(
+    r: &Ray,
+    rec: &HitRecord,
+    material_pdf: &ScatterPDF,
+    world: &World,
+    sampler: &mut dyn Sampler,
+) -> Color {
+    let Some((direction, light_pdf, emitted)) = trace_light_sample(r, rec, world, sampler) else {
+        return Color::ZERO;
+    };
+
+    let shadow_ray = Ray::new_with_time(rec.p, direction, r.time);
+    let scattering_pdf = rec.material.scattering_pdf(r, rec, &shadow_ray);
+    let weight = power_heuristic(light_pdf, material_pdf.value(direction));
+    emitted * scattering_pdf * weight / light_pdf
+}
+
+/// Like [`sample_light`], but for when the light sample is all there is.
+fn sample_light_only(r: &Ray, rec: &HitRecord, world: &World, sampler: &mut dyn Sampler) -> Color {
+    let Some((direction, light_pdf, emitted)) = trace_light_sample(r, rec, world, sampler) else {
+        return Color::ZERO;
+    };
+
+    let shadow_ray = Ray::new_with_time(rec.p, direction, r.time);
+    emitted * rec.material.scattering_pdf(r, rec, &shadow_ray) / light_pdf
+}
+
+/// Picks a direction towards the lights from `rec` and returns it with its density and the
+/// light arriving from it, if any does.
+fn trace_light_sample(
+    r: &Ray,
+    rec: &HitRecord,
+    world: &World,
+    sampler: &mut dyn Sampler,
+) -> Option<(glam::Vec3A, f32, Color)> {
+    let direction = world.lights.random(rec.p, sampler);
+    let light_pdf = world.lights.pdf_value(rec.p, direction);
+    if light_pdf <= 0.0 {
+        return None;
+    }
+
+    // Whatever the shadow ray hits first has to be emissive for light to get through.
+    let shadow_ray = Ray::new_with_time(rec.p, direction, r.time);
+    let light = world.hit(&shadow_ray)?;
+    let emitted = light.material.emitted(light.u, light.v, light.p);
+    (emitted != Color::ZERO).then_some((direction, light_pdf, emitted))
+}
+
+/// Multiple importance sampling weight of a strategy that picked a direction with density `pdf`,
+/// when `other_pdf` is the density of the other strategy.
+fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b > 0.0 {
+        a / (a + b)
+    } else {
+        0.0
+    }
+}
@@ -1,11 +1,23 @@
 use util::color::Color;
 
+pub mod aov;
 pub mod bvh;
 pub mod camera;
+pub mod checkpoint;
+pub mod denoise;
+pub mod filter;
+pub mod framebuffer;
 pub mod hittable;
+pub mod import;
+pub mod integrator;
 pub mod material;
+pub mod sampler;
+pub mod scene;
+pub mod scene_file;
 pub mod scenes;
 pub mod texture;
+pub mod tile;
 pub mod util;
 
+/// Seed for the random numbers of scenes and, by default, of the samples when rendering.
 pub const SEED: u64 = 2;
@@ -1,12 +1,276 @@
-use anyhow::Result;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
-use ray_tracing::scenes;
+use anyhow::{anyhow, bail, Result};
+use clap::Parser;
+
+use ray_tracing::{
+    aov::Aov,
+    camera::{AdaptiveSampling, CameraOverrides, Progressive},
+    denoise::Denoiser,
+    filter::FilterKind,
+    integrator::IntegratorKind,
+    sampler::SamplerKind,
+    scene::Scene,
+    scenes,
+    tile::TileOrder,
+    util::{
+        color::{DisplayTransform, ToneMap, Transfer},
+        Point3,
+    },
+};
+
+#[derive(Parser, Debug)]
+#[command(about = "Renders one of the built-in scenes or a TOML scene file.")]
+struct Args {
+    /// Name of the scene to render, see `--list`, or the path to a `.toml` scene file or a
+    /// `.gltf` or `.glb` file.
+    #[arg(default_value = "random_spheres")]
+    scene: String,
+
+    /// Print the names of every built-in scene and exit.
+    #[arg(long)]
+    list: bool,
+
+    /// Width of the rendered image in pixels.
+    #[arg(long)]
+    width: Option<i32>,
+    /// Random samples per pixel. With `--tolerance`, pixels that converge earlier stop there and
+    /// ones that haven't converged by then go on up to `--max-spp`.
+    #[arg(long)]
+    spp: Option<i32>,
+    /// Stop sampling a pixel once the relative standard error of its brightness is at most this.
+    #[arg(long)]
+    tolerance: Option<f32>,
+    /// Samples every pixel gets before `--tolerance` is checked.
+    #[arg(long, default_value_t = 16)]
+    min_spp: u32,
+    /// Samples a pixel that hasn't converged gets at most with `--tolerance`, 4 times `--spp`
+    /// if unset.
+    #[arg(long)]
+    max_spp: Option<u32>,
+    /// Maximum number of ray bounces into scene. The path integrator ends paths by Russian
+    /// roulette instead.
+    #[arg(long)]
+    max_depth: Option<i32>,
+    /// Bounces before paths may be ended early by Russian roulette.
+    #[arg(long, default_value_t = 3)]
+    roulette_depth: i32,
+    /// How light along camera rays is worked out: naive, path, path-recursive, ao, direct,
+    /// normals, uv, depth or bvh-cost.
+    #[arg(long, default_value = "path")]
+    integrator: IntegratorKind,
+    /// How far away occluders count for the ao integrator, a tenth of the size of the scene by
+    /// default.
+    #[arg(long)]
+    ao_distance: Option<f32>,
+    /// Vertical FOV in degrees.
+    #[arg(long)]
+    vfov: Option<f32>,
+    /// Point the camera is looking from, as `x,y,z`.
+    #[arg(long, value_parser = parse_point, allow_hyphen_values = true)]
+    lookfrom: Option<Point3>,
+    /// Point the camera is looking at, as `x,y,z`.
+    #[arg(long, value_parser = parse_point, allow_hyphen_values = true)]
+    lookat: Option<Point3>,
+    /// Variation angle of rays through each pixel.
+    #[arg(long)]
+    defocus_angle: Option<f32>,
+
+    /// Where the random numbers of the samples come from: independent, stratified, halton or
+    /// sobol.
+    #[arg(long, default_value = "independent")]
+    sampler: SamplerKind,
+    /// Seed for the random numbers of the samples, the same seed gives the same image.
+    #[arg(long, default_value_t = ray_tracing::SEED)]
+    seed: u64,
+
+    /// Number of render threads.
+    #[arg(long, default_value_t = 8)]
+    threads: usize,
+    /// Width and height in pixels of the tiles the image is rendered in.
+    #[arg(long, default_value_t = 16)]
+    tile_size: u32,
+    /// Order the tiles are rendered in: scanline, spiral or hilbert.
+    #[arg(long, default_value = "scanline")]
+    tile_order: TileOrder,
+    /// How samples are weighted into the pixels around them: box, tent, gaussian, mitchell or
+    /// lanczos.
+    #[arg(long, default_value = "box")]
+    filter: FilterKind,
+    /// Radius of the filter in pixels, 0.5 for box, 1 for tent, 1.5 for gaussian and 2 for
+    /// mitchell and lanczos by default.
+    #[arg(long)]
+    filter_radius: Option<f32>,
+    /// Where the finished image is written, the format is picked from the extension.
+    /// Use `.exr`, `.hdr` or `.pfm` to keep radiance above 1.0.
+    #[arg(short, long, default_value = "image.jpg")]
+    output: PathBuf,
+    /// AOVs to write along with the image, as layers of an `.exr` output or files next to it:
+    /// albedo, normal, position, depth, object_id or material_id, separated by commas.
+    #[arg(long, value_delimiter = ',')]
+    aov: Vec<Aov>,
+    /// Denoise the finished image, guided by its albedo, normals and depth.
+    #[arg(long)]
+    denoise: bool,
+    /// Denoiser iterations, each blurs twice as far as the one before.
+    #[arg(long, default_value_t = Denoiser::default().iterations)]
+    denoise_iterations: u32,
+    /// How different colors may be and still be blurred together by the denoiser.
+    #[arg(long, default_value_t = Denoiser::default().color_sigma)]
+    denoise_sigma: f32,
+    /// How many times brighter than its neighbors a pixel must be for the denoiser to treat it
+    /// as a firefly, 0 to keep every pixel.
+    #[arg(long, default_value_t = Denoiser::default().firefly_ratio)]
+    firefly_ratio: f32,
+    /// Exposure adjustment in stops for 8-bit output.
+    #[arg(long, default_value_t = 0.0, allow_hyphen_values = true)]
+    exposure: f32,
+    /// Tone map for 8-bit output: clamp, reinhard or aces.
+    #[arg(long, default_value = "clamp")]
+    tone_map: ToneMap,
+    /// Transfer function for 8-bit output: gamma2, srgb or linear.
+    #[arg(long, default_value = "gamma2")]
+    transfer: Transfer,
+    /// Keep the samples of every pixel in this file, so an interrupted render can be resumed
+    /// or a finished one continued with a higher `--spp`.
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+    /// Render in passes of this many samples per pixel, showing the whole image early.
+    #[arg(long)]
+    pass_spp: Option<u32>,
+    /// Stop rendering after the pass that runs past this many seconds.
+    #[arg(long)]
+    time_budget: Option<f32>,
+    /// Stop rendering once the average relative error of the pixels is at most this.
+    #[arg(long)]
+    noise_threshold: Option<f32>,
+    /// Write the image after every pass, not just at the end.
+    #[arg(long)]
+    save_passes: bool,
+    /// Write the scene, with any camera overrides, to a TOML scene file instead of rendering it.
+    #[arg(long)]
+    export: Option<PathBuf>,
+}
+
+fn parse_point(s: &str) -> Result<Point3> {
+    let components = s
+        .split(',')
+        .map(|c| c.trim().parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match components[..] {
+        [x, y, z] => Ok(Point3::new(x, y, z)),
+        _ => Err(anyhow!("expected three comma separated numbers, got `{s}`")),
+    }
+}
 
 fn main() -> Result<()> {
+    let args = Args::parse();
+
+    if args.list {
+        for (name, _) in scenes::SCENES {
+            println!("{name}");
+        }
+        return Ok(());
+    }
+
+    let scene_path = PathBuf::from(&args.scene);
+    let is_scene_file = scene_path
+        .extension()
+        .is_some_and(|ext| ext == "toml" || ext == "gltf" || ext == "glb");
+    let builder = scenes::find(&args.scene);
+    if builder.is_none() && !is_scene_file {
+        bail!(
+            "Unknown scene `{}`, use `--list` to see the available scenes.",
+            args.scene
+        );
+    }
+
+    let time_budget = args
+        .time_budget
+        .map(|secs| {
+            Duration::try_from_secs_f32(secs).map_err(|_| {
+                anyhow!("Invalid time budget `{secs}`, it has to be a number of seconds.")
+            })
+        })
+        .transpose()?;
+
     rayon::ThreadPoolBuilder::new()
-        .num_threads(8)
+        .num_threads(args.threads)
         .build_global()
         .unwrap();
 
-    scenes::random_spheres()
+    let mut scene = match builder {
+        Some(builder) => builder()?,
+        None => Scene::load(&scene_path)?,
+    };
+
+    let overrides = CameraOverrides {
+        image_width: args.width,
+        samples_per_pixel: args.spp,
+        max_depth: args.max_depth,
+        vfov: args.vfov,
+        lookfrom: args.lookfrom,
+        lookat: args.lookat,
+        defocus_angle: args.defocus_angle,
+    };
+    overrides.apply(&mut scene.camera);
+    scene.camera.checkpoint_path = args.checkpoint;
+    scene.camera.roulette_depth = args.roulette_depth;
+    scene.camera.integrator = args.integrator;
+    scene.camera.ao_distance = args.ao_distance;
+    scene.camera.sampler = args.sampler;
+    scene.camera.aovs = args.aov;
+    scene.camera.denoiser = args.denoise.then(|| Denoiser {
+        iterations: args.denoise_iterations,
+        color_sigma: args.denoise_sigma,
+        firefly_ratio: args.firefly_ratio,
+        ..Default::default()
+    });
+    scene.camera.seed = args.seed;
+    scene.camera.tile_size = args.tile_size;
+    scene.camera.tile_order = args.tile_order;
+    scene.camera.filter = args.filter;
+    scene.camera.filter_radius = args.filter_radius;
+    let samples_per_pixel = scene.camera.samples_per_pixel.max(0) as u32;
+    scene.camera.adaptive = args.tolerance.map(|tolerance| AdaptiveSampling {
+        min_samples: args.min_spp,
+        tolerance,
+        max_samples: args.max_spp.unwrap_or(4 * samples_per_pixel),
+    });
+    if args.pass_spp.is_some() || args.time_budget.is_some() || args.noise_threshold.is_some() {
+        scene.camera.progressive = Some(Progressive {
+            samples_per_pass: args.pass_spp.unwrap_or(1),
+            time_budget,
+            noise_threshold: args.noise_threshold,
+        });
+    }
+
+    if let Some(path) = args.export {
+        return scene.save(path);
+    }
+
+    let display = DisplayTransform {
+        exposure: args.exposure,
+        tone_map: args.tone_map,
+        transfer: args.transfer,
+    };
+
+    let now = Instant::now();
+    let mut framebuffer = scene.render_progressive(|_, framebuffer| {
+        if !args.save_passes {
+            return Ok(());
+        }
+        let mut framebuffer = framebuffer.clone();
+        framebuffer.display = display;
+        framebuffer.save(&args.output)
+    })?;
+    framebuffer.display = display;
+
+    println!("Writing {} pixels...", framebuffer.pixels.len());
+    framebuffer.save(&args.output)?;
+    println!("Completed in {}s.", now.elapsed().as_secs_f32());
+
+    Ok(())
 }
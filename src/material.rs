@@ -1,12 +1,45 @@
-use std::sync::Arc;
+// The constructors of the materials wrap them in `Material` right away.
+#![allow(clippy::new_ret_no_self)]
 
-use crate::{hittable::HitRecord, texture::Texture, util::all::*, Color};
+use std::{f32::consts::PI, sync::Arc};
+
+use crate::{
+    hittable::HitRecord,
+    sampler::{sample_unit_vector, Sampler},
+    texture::Texture,
+    util::{
+        all::*,
+        pdf::{CosinePDF, ScatterPDF, SpherePDF},
+    },
+    Color,
+};
 
 pub trait MaterialT {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)>;
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord>;
+    /// Density, per unit solid angle, of light arriving along `r_in` leaving along `scattered`.
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f32 {
+        0.0
+    }
     fn emitted(&self, _u: f32, _v: f32, _p: Point3) -> Color {
         Color::ZERO
     }
+    /// Overall color of the material, for the albedo AOV.
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color::ZERO
+    }
+}
+
+/// How a material scatters light arriving at a hit.
+pub enum ScatterRecord {
+    /// In directions from a distribution, see [`MaterialT::scattering_pdf`].
+    Pdf { attenuation: Color, pdf: ScatterPDF },
+    /// In one direction, like a mirror.
+    Specular { attenuation: Color, ray: Ray },
 }
 
 #[derive(Clone)]
@@ -16,16 +49,45 @@ pub enum Material {
     Dielectric(Dielectric),
     DiffuseLight(DiffuseLight),
     Isotropic(Isotropic),
+    PhysicallyBased(PhysicallyBased),
+}
+
+impl Material {
+    /// Whether the material emits light, so surfaces of it should be sampled as lights.
+    pub fn is_emissive(&self) -> bool {
+        match self {
+            Material::DiffuseLight(_) => true,
+            Material::PhysicallyBased(pb) => !pb.emission.is_black(),
+            _ => false,
+        }
+    }
 }
 
 impl MaterialT for Material {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        match self {
+            Material::Lambertian(l) => l.scatter(r_in, rec, sampler),
+            Material::Metal(m) => m.scatter(r_in, rec, sampler),
+            Material::Dielectric(d) => d.scatter(r_in, rec, sampler),
+            Material::DiffuseLight(dl) => dl.scatter(r_in, rec, sampler),
+            Material::Isotropic(i) => i.scatter(r_in, rec, sampler),
+            Material::PhysicallyBased(pb) => pb.scatter(r_in, rec, sampler),
+        }
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f32 {
         match self {
-            Material::Lambertian(l) => l.scatter(r_in, rec),
-            Material::Metal(m) => m.scatter(r_in, rec),
-            Material::Dielectric(d) => d.scatter(r_in, rec),
-            Material::DiffuseLight(dl) => dl.scatter(r_in, rec),
-            Material::Isotropic(i) => i.scatter(r_in, rec),
+            Material::Lambertian(l) => l.scattering_pdf(r_in, rec, scattered),
+            Material::Metal(m) => m.scattering_pdf(r_in, rec, scattered),
+            Material::Dielectric(d) => d.scattering_pdf(r_in, rec, scattered),
+            Material::DiffuseLight(dl) => dl.scattering_pdf(r_in, rec, scattered),
+            Material::Isotropic(i) => i.scattering_pdf(r_in, rec, scattered),
+            Material::PhysicallyBased(pb) => pb.scattering_pdf(r_in, rec, scattered),
         }
     }
 
@@ -36,6 +98,18 @@ impl MaterialT for Material {
             Material::Dielectric(d) => d.emitted(u, v, p),
             Material::DiffuseLight(dl) => dl.emitted(u, v, p),
             Material::Isotropic(i) => i.emitted(u, v, p),
+            Material::PhysicallyBased(pb) => pb.emitted(u, v, p),
+        }
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        match self {
+            Material::Lambertian(l) => l.albedo(rec),
+            Material::Metal(m) => m.albedo(rec),
+            Material::Dielectric(d) => d.albedo(rec),
+            Material::DiffuseLight(dl) => dl.albedo(rec),
+            Material::Isotropic(i) => i.albedo(rec),
+            Material::PhysicallyBased(pb) => pb.albedo(rec),
         }
     }
 }
@@ -54,17 +128,25 @@ impl Lambertian {
 }
 
 impl MaterialT for Lambertian {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
-        let mut scatter_dir = rec.normal + random_unit_vector();
+    fn scatter(
+        &self,
+        _r_in: &Ray,
+        rec: &HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        Some(ScatterRecord::Pdf {
+            attenuation: self.albedo.sample_hit(rec),
+            pdf: ScatterPDF::Cosine(CosinePDF::new(&rec.normal)),
+        })
+    }
 
-        if vec3a_near_zero(&scatter_dir) {
-            scatter_dir = rec.normal;
-        }
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f32 {
+        let cos_theta = rec.normal.dot(scattered.direction.normalize());
+        (cos_theta / PI).max(0.0)
+    }
 
-        Some((
-            Ray::new_with_time(rec.p, scatter_dir, r_in.time),
-            self.albedo.sample(rec.u, rec.v, rec.p),
-        ))
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.albedo.sample_hit(rec)
     }
 }
 
@@ -84,22 +166,27 @@ impl Metal {
 }
 
 impl MaterialT for Metal {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
         let reflected = reflect(r_in.direction.normalize(), rec.normal);
-        let out = (
-            Ray::new_with_time(
-                rec.p,
-                reflected + self.fuzz * random_unit_vector(),
-                r_in.time,
-            ),
-            self.albedo,
+        let ray = Ray::new_with_time(
+            rec.p,
+            reflected + self.fuzz * sample_unit_vector(sampler.get_2d()),
+            r_in.time,
         );
 
-        if out.0.direction.dot(rec.normal) > 0.0 {
-            Some(out)
-        } else {
-            None
-        }
+        (ray.direction.dot(rec.normal) > 0.0).then_some(ScatterRecord::Specular {
+            attenuation: self.albedo,
+            ray,
+        })
+    }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        self.albedo
     }
 }
 
@@ -121,7 +208,12 @@ impl Dielectric {
 }
 
 impl MaterialT for Dielectric {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
         let refraction_ratio = if rec.front_face {
             1.0 / self.ir
         } else {
@@ -133,14 +225,21 @@ impl MaterialT for Dielectric {
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
         let direction = if (refraction_ratio * sin_theta > 1.0)
-            || (Self::reflectance(cos_theta, refraction_ratio) > rand::random())
+            || (Self::reflectance(cos_theta, refraction_ratio) > sampler.get_1d())
         {
             reflect(unit_dir, rec.normal)
         } else {
             refract(unit_dir, rec.normal, refraction_ratio)
         };
 
-        Some((Ray::new_with_time(rec.p, direction, r_in.time), Color::ONE))
+        Some(ScatterRecord::Specular {
+            attenuation: Color::ONE,
+            ray: Ray::new_with_time(rec.p, direction, r_in.time),
+        })
+    }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color::ONE
     }
 }
 
@@ -158,13 +257,22 @@ impl DiffuseLight {
 }
 
 impl MaterialT for DiffuseLight {
-    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Color)> {
+    fn scatter(
+        &self,
+        _r_in: &Ray,
+        _rec: &HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
         None
     }
 
     fn emitted(&self, u: f32, v: f32, p: Point3) -> Color {
         self.emit.sample(u, v, p)
     }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.emitted(rec.u, rec.v, rec.p).min(Color::ONE)
+    }
 }
 
 #[derive(Clone)]
@@ -181,10 +289,107 @@ impl Isotropic {
 }
 
 impl MaterialT for Isotropic {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
-        let scattered = Ray::new_with_time(rec.p, random_unit_vector(), r_in.time);
-        let attenuation = self.albedo.sample(rec.u, rec.v, rec.p);
+    fn scatter(
+        &self,
+        _r_in: &Ray,
+        rec: &HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        Some(ScatterRecord::Pdf {
+            attenuation: self.albedo.sample_hit(rec),
+            pdf: ScatterPDF::Sphere(SpherePDF),
+        })
+    }
+
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f32 {
+        1.0 / (4.0 * PI)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.albedo.sample_hit(rec)
+    }
+}
+
+/// The metallic-roughness material of glTF, a blend of a diffuse dielectric with a glossy coat
+/// and a glossy metal.
+#[derive(Clone)]
+pub struct PhysicallyBased {
+    pub base_color: Arc<dyn Texture>,
+    /// Roughness in the green channel and metalness in the blue, the way glTF packs them.
+    pub metallic_roughness: Arc<dyn Texture>,
+    pub emission: Arc<dyn Texture>,
+}
+
+impl PhysicallyBased {
+    pub fn new(
+        base_color: impl Texture + 'static,
+        metallic_roughness: impl Texture + 'static,
+        emission: impl Texture + 'static,
+    ) -> Material {
+        Material::PhysicallyBased(Self {
+            base_color: Arc::new(base_color),
+            metallic_roughness: Arc::new(metallic_roughness),
+            emission: Arc::new(emission),
+        })
+    }
+}
+
+impl MaterialT for PhysicallyBased {
+    /// Reflects off the glossy part or scatters off the diffuse part, picked by how much each
+    /// reflects.
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        let base_color = self.base_color.sample_hit(rec);
+        let metallic_roughness = self.metallic_roughness.sample_hit(rec);
+        let roughness = metallic_roughness.y.clamp(0.0, 1.0);
+        let metallic = metallic_roughness.z.clamp(0.0, 1.0);
+
+        // Dielectrics reflect 4% head on, metals their color.
+        let unit_dir = r_in.direction.normalize();
+        let cos_theta = (-unit_dir).dot(rec.normal).clamp(0.0, 1.0);
+        let f0 = Color::splat(0.04).lerp(base_color, metallic);
+        let fresnel = f0 + (Color::ONE - f0) * (1.0 - cos_theta).powi(5);
+        let diffuse = (1.0 - metallic) * (Color::ONE - fresnel) * base_color;
+
+        let specular_weight = luminance(fresnel);
+        let diffuse_weight = luminance(diffuse);
+        let specular_chance =
+            specular_weight / (specular_weight + diffuse_weight).max(f32::EPSILON);
+
+        if sampler.get_1d() < specular_chance {
+            let reflected = reflect(unit_dir, rec.normal);
+            let fuzz = roughness * roughness;
+            let ray = Ray::new_with_time(
+                rec.p,
+                reflected + fuzz * sample_unit_vector(sampler.get_2d()),
+                r_in.time,
+            );
+            (ray.direction.dot(rec.normal) > 0.0).then_some(ScatterRecord::Specular {
+                attenuation: fresnel / specular_chance,
+                ray,
+            })
+        } else {
+            Some(ScatterRecord::Pdf {
+                attenuation: diffuse / (1.0 - specular_chance),
+                pdf: ScatterPDF::Cosine(CosinePDF::new(&rec.normal)),
+            })
+        }
+    }
+
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f32 {
+        let cos_theta = rec.normal.dot(scattered.direction.normalize());
+        (cos_theta / PI).max(0.0)
+    }
+
+    fn emitted(&self, u: f32, v: f32, p: Point3) -> Color {
+        self.emission.sample(u, v, p)
+    }
 
-        Some((scattered, attenuation))
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.base_color.sample_hit(rec)
     }
 }
@@ -0,0 +1,341 @@
+//! Where the random numbers of a sample come from.
+//!
+//! A sample asks its [`Sampler`] for a series of 1D and 2D values in `[0, 1)`: first the position
+//! in the pixel, then the lens and the time, then whatever the materials along the path need.
+//! Low discrepancy samplers spread these values more evenly over the samples of a pixel than
+//! independent random numbers do, which gives less noise for the same number of samples.
+
+use std::f32::consts::PI;
+use std::str::FromStr;
+
+use anyhow::{bail, Error};
+use glam::{Vec2, Vec3A};
+
+use crate::util::random::{mix64, random_f32};
+
+pub trait Sampler {
+    /// Starts sample `index` of the pixel with the given index.
+    fn start_sample(&mut self, pixel: u64, index: u32);
+    /// The next value of the sample.
+    fn get_1d(&mut self) -> f32;
+    /// The next two values of the sample, which are well distributed together.
+    fn get_2d(&mut self) -> Vec2;
+}
+
+/// The samplers a render can use.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SamplerKind {
+    /// Independent uniform random numbers.
+    #[default]
+    Independent,
+    /// Jittered samples in shuffled strata.
+    Stratified,
+    /// The Halton sequence, randomly shifted for every pixel.
+    Halton,
+    /// The Sobol sequence with hash based Owen scrambling.
+    Sobol,
+}
+
+impl SamplerKind {
+    /// A sampler for pixels that get up to `samples_per_pixel` samples.
+    pub fn create(self, seed: u64, samples_per_pixel: u32) -> Box<dyn Sampler> {
+        let state = SampleState::new(seed);
+        match self {
+            SamplerKind::Independent => Box::new(IndependentSampler),
+            SamplerKind::Stratified => Box::new(StratifiedSampler::new(state, samples_per_pixel)),
+            SamplerKind::Halton => Box::new(HaltonSampler { state }),
+            SamplerKind::Sobol => Box::new(SobolSampler { state }),
+        }
+    }
+}
+
+impl FromStr for SamplerKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "independent" => SamplerKind::Independent,
+            "stratified" => SamplerKind::Stratified,
+            "halton" => SamplerKind::Halton,
+            "sobol" => SamplerKind::Sobol,
+            _ => bail!("unknown sampler `{s}`, expected independent, stratified, halton or sobol"),
+        })
+    }
+}
+
+/// Uniform random numbers, from the generator of the current thread.
+pub struct IndependentSampler;
+
+impl Sampler for IndependentSampler {
+    fn start_sample(&mut self, _pixel: u64, _index: u32) {}
+
+    fn get_1d(&mut self) -> f32 {
+        random_f32(0.0, 1.0)
+    }
+
+    fn get_2d(&mut self) -> Vec2 {
+        Vec2::new(random_f32(0.0, 1.0), random_f32(0.0, 1.0))
+    }
+}
+
+/// What the deterministic samplers need to know about the current sample.
+struct SampleState {
+    seed: u64,
+    pixel_seed: u64,
+    index: u32,
+    dimension: u32,
+}
+
+impl SampleState {
+    fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel_seed: 0,
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn start_sample(&mut self, pixel: u64, index: u32) {
+        self.pixel_seed = mix64(self.seed ^ mix64(pixel));
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    /// Moves on to the next `count` dimensions, returning the first and a hash that is the same
+    /// for every sample of the pixel but differs between pixels and dimensions.
+    fn next_dimensions(&mut self, count: u32) -> (u32, u32) {
+        let dimension = self.dimension;
+        self.dimension += count;
+        let hash = mix64(self.pixel_seed ^ dimension as u64);
+        (dimension, hash as u32)
+    }
+}
+
+/// Jittered samples in a random stratum, every stratum is used once every `samples_per_pixel`
+/// samples.
+pub struct StratifiedSampler {
+    state: SampleState,
+    strata: u32,
+    /// Strata per side in two dimensions.
+    strata_2d: u32,
+}
+
+impl StratifiedSampler {
+    fn new(state: SampleState, samples_per_pixel: u32) -> Self {
+        let strata = samples_per_pixel.max(1);
+        Self {
+            state,
+            strata,
+            strata_2d: (strata as f32).sqrt() as u32,
+        }
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_sample(&mut self, pixel: u64, index: u32) {
+        self.state.start_sample(pixel, index);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let (_, hash) = self.state.next_dimensions(1);
+        let stratum = permute(self.state.index % self.strata, self.strata, hash);
+        (stratum as f32 + random_f32(0.0, 1.0)) / self.strata as f32
+    }
+
+    fn get_2d(&mut self) -> Vec2 {
+        let (_, hash) = self.state.next_dimensions(2);
+        let n = self.strata_2d;
+        let stratum = permute(self.state.index % (n * n), n * n, hash);
+        let jitter = Vec2::new(random_f32(0.0, 1.0), random_f32(0.0, 1.0));
+        (Vec2::new((stratum % n) as f32, (stratum / n) as f32) + jitter) / n as f32
+    }
+}
+
+/// The Halton sequence, with every dimension of every pixel shifted by a random amount.
+pub struct HaltonSampler {
+    state: SampleState,
+}
+
+impl HaltonSampler {
+    const PRIMES: [u32; 32] = [
+        2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89,
+        97, 101, 103, 107, 109, 113, 127, 131,
+    ];
+
+    fn value(&self, dimension: u32, hash: u32) -> f32 {
+        let base = Self::PRIMES[dimension as usize % Self::PRIMES.len()];
+        let shift = to_unit_float(hash);
+        (radical_inverse(base, self.state.index) + shift).fract()
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_sample(&mut self, pixel: u64, index: u32) {
+        self.state.start_sample(pixel, index);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let (dimension, hash) = self.state.next_dimensions(1);
+        self.value(dimension, hash)
+    }
+
+    fn get_2d(&mut self) -> Vec2 {
+        let (dimension, hash) = self.state.next_dimensions(2);
+        Vec2::new(
+            self.value(dimension, hash),
+            self.value(dimension + 1, hash.rotate_left(16)),
+        )
+    }
+}
+
+/// The first two Sobol dimensions, shuffled and Owen scrambled separately for every pair of
+/// dimensions of every pixel, following Burley's "Practical Hash-based Owen Scrambling".
+pub struct SobolSampler {
+    state: SampleState,
+}
+
+impl SobolSampler {
+    fn value(&self, dimension: usize, hash: u32) -> u32 {
+        let index = nested_uniform_scramble(self.state.index, hash);
+        let x = SOBOL_DIRECTIONS[dimension]
+            .iter()
+            .enumerate()
+            .filter(|(bit, _)| index >> bit & 1 == 1)
+            .fold(0, |x, (_, direction)| x ^ direction);
+        nested_uniform_scramble(x, mix64(hash as u64 ^ dimension as u64) as u32)
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_sample(&mut self, pixel: u64, index: u32) {
+        self.state.start_sample(pixel, index);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let (_, hash) = self.state.next_dimensions(1);
+        to_unit_float(self.value(0, hash))
+    }
+
+    fn get_2d(&mut self) -> Vec2 {
+        let (_, hash) = self.state.next_dimensions(2);
+        Vec2::new(
+            to_unit_float(self.value(0, hash)),
+            to_unit_float(self.value(1, hash)),
+        )
+    }
+}
+
+/// Direction numbers of the first two Sobol dimensions, the van der Corput sequence and the one
+/// from the primitive polynomial `x + 1`.
+const SOBOL_DIRECTIONS: [[u32; 32]; 2] = {
+    let mut directions = [[0; 32]; 2];
+    let mut bit = 0;
+    while bit < 32 {
+        directions[0][bit] = 1 << (31 - bit);
+        directions[1][bit] = if bit == 0 {
+            1 << 31
+        } else {
+            directions[1][bit - 1] ^ (directions[1][bit - 1] >> 1)
+        };
+        bit += 1;
+    }
+    directions
+};
+
+/// Maps 32 random bits to `[0, 1)`.
+fn to_unit_float(x: u32) -> f32 {
+    (x >> 8) as f32 / (1 << 24) as f32
+}
+
+/// Mirrors the digits of `index` in `base` around the decimal point.
+fn radical_inverse(base: u32, mut index: u32) -> f32 {
+    let inv_base = 1.0 / base as f64;
+    let (mut reversed, mut scale) = (0.0, inv_base);
+    while index > 0 {
+        reversed += (index % base) as f64 * scale;
+        index /= base;
+        scale *= inv_base;
+    }
+    (reversed as f32).min(1.0 - f32::EPSILON)
+}
+
+/// Owen scrambling of the bits of `x`, the same for equal hashes.
+fn nested_uniform_scramble(x: u32, hash: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), hash).reverse_bits()
+}
+
+/// Scrambles the bits of `x` such that each only depends on the bits below it.
+fn laine_karras_permutation(mut x: u32, hash: u32) -> u32 {
+    x = x.wrapping_add(hash);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x
+}
+
+/// Element `i` of a random permutation of `0..len`, from Kensler's "Correlated Multi-Jittered
+/// Sampling".
+fn permute(mut i: u32, len: u32, p: u32) -> u32 {
+    let mut w = len - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < len {
+            return (i + p) % len;
+        }
+    }
+}
+
+/// A uniformly distributed direction.
+pub fn sample_unit_vector(u: Vec2) -> Vec3A {
+    let z = 1.0 - 2.0 * u.x;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.y;
+    Vec3A::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// A direction around +Z with a density proportional to the cosine of its angle to +Z.
+pub fn sample_cosine_direction(u: Vec2) -> Vec3A {
+    let phi = 2.0 * PI * u.x;
+    let r = u.y.sqrt();
+    Vec3A::new(phi.cos() * r, phi.sin() * r, (1.0 - u.y).sqrt())
+}
+
+/// A uniformly distributed point in the unit disk on the xy plane, using the concentric mapping
+/// so strata stay compact.
+pub fn sample_unit_disk(u: Vec2) -> Vec3A {
+    let offset = 2.0 * u - Vec2::ONE;
+    if offset == Vec2::ZERO {
+        return Vec3A::ZERO;
+    }
+
+    let (r, theta) = if offset.x.abs() > offset.y.abs() {
+        (offset.x, PI / 4.0 * (offset.y / offset.x))
+    } else {
+        (offset.y, PI / 2.0 - PI / 4.0 * (offset.x / offset.y))
+    };
+    Vec3A::new(r * theta.cos(), r * theta.sin(), 0.0)
+}
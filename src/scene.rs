@@ -0,0 +1,197 @@
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bvh::{aabb::AABB, node::BVHNode},
+    camera::{Camera, PassReport},
+    checkpoint::scene_hash,
+    framebuffer::Framebuffer,
+    hittable::{hittable_list::HittableList, Hittable},
+    import::gltf,
+    scene_file::{CameraDesc, ObjectDesc, SceneFile},
+    util::{color::Color, Point3},
+};
+
+/// Descriptive information about a scene that doesn't affect how it renders.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SceneMetadata {
+    pub name: String,
+    pub description: String,
+}
+
+/// A built world together with the camera that looks at it.
+pub struct Scene {
+    pub metadata: SceneMetadata,
+    pub camera: Camera,
+    pub world: BVHNode,
+    /// Emissive objects in the world, which are also part of `world`.
+    pub lights: HittableList,
+
+    /// The description the world was built from.
+    description: SceneFile,
+    /// Directory that relative paths in the description are resolved against.
+    base_dir: PathBuf,
+}
+
+impl Scene {
+    /// Builds a scene from a description, resolving relative paths against the working directory.
+    pub fn new(description: SceneFile) -> Result<Self> {
+        let (world, lights) = description.build(Path::new(""))?;
+        Ok(Self::from_parts(description, Path::new(""), world, lights))
+    }
+
+    /// Loads a scene from a TOML scene file, or from a glTF file to render as it is.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gltf" | "glb") => Self::from_gltf(path),
+            _ => crate::scene_file::load(path),
+        }
+    }
+
+    /// A scene of the meshes of a glTF file, seen through its first camera or from in front of
+    /// them if it has none.
+    fn from_gltf(path: &Path) -> Result<Self> {
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        let Some(file_name) = path.file_name() else {
+            bail!("{} is not a file", path.display());
+        };
+        let mut description = SceneFile {
+            metadata: SceneMetadata {
+                name: path
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned(),
+                description: format!("The scene of {}.", file_name.to_string_lossy()),
+            },
+            objects: vec![ObjectDesc::Mesh {
+                path: file_name.into(),
+                material: None,
+            }],
+            ..Default::default()
+        };
+        let (world, lights) = description.build(base_dir)?;
+
+        // Files rarely have lights of their own, light them with a sky.
+        let mut camera = description.camera.to_camera();
+        camera.background = Color::new(0.7, 0.8, 1.0);
+        match gltf::load_cameras(path)?.first() {
+            Some(settings) => settings.apply(&mut camera),
+            None => frame(&mut camera, world.bounding_box()),
+        }
+        description.camera = CameraDesc::from(&camera);
+        Ok(Self::from_parts(description, base_dir, world, lights))
+    }
+
+    pub(crate) fn from_parts(
+        description: SceneFile,
+        base_dir: &Path,
+        world: BVHNode,
+        lights: HittableList,
+    ) -> Self {
+        Self {
+            metadata: description.metadata.clone(),
+            camera: description.camera.to_camera(),
+            world,
+            lights,
+            description,
+            base_dir: base_dir.to_owned(),
+        }
+    }
+
+    /// The description the world was built from.
+    pub fn description(&self) -> &SceneFile {
+        &self.description
+    }
+
+    /// Changes the description and rebuilds the world and lights from it.
+    ///
+    /// The camera and metadata are kept as they are.
+    pub fn rebuild(&mut self, f: impl FnOnce(&mut SceneFile)) -> Result<()> {
+        let mut description = self.description.clone();
+        f(&mut description);
+
+        let (world, lights) = description.build(&self.base_dir)?;
+        self.world = world;
+        self.lights = lights;
+        self.description = description;
+        Ok(())
+    }
+
+    /// The scene as it currently is, including any changes to the camera or metadata.
+    pub fn to_scene_file(&self) -> SceneFile {
+        SceneFile {
+            metadata: self.metadata.clone(),
+            camera: CameraDesc::from(&self.camera),
+            ..self.description.clone()
+        }
+    }
+
+    /// Writes the scene to a TOML scene file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_scene_file().to_toml()?)
+            .with_context(|| format!("Failed to write scene file {}", path.display()))
+    }
+
+    /// Identifies the world of the scene, ignoring the camera and metadata.
+    ///
+    /// The files the scene refers to count by their size and modification time, so editing one
+    /// changes the hash. Files that meshes load themselves, like OBJ materials, aren't looked at.
+    pub fn hash(&self) -> Result<u64> {
+        let world = SceneFile {
+            metadata: Default::default(),
+            camera: Default::default(),
+            ..self.description.clone()
+        };
+        let mut description = world.to_toml()?;
+        for file in world.files() {
+            let path = self.base_dir.join(file);
+            let metadata = std::fs::metadata(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let modified = metadata
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            description += &format!(
+                "\n# {} {} {}",
+                file.display(),
+                metadata.len(),
+                modified.as_nanos()
+            );
+        }
+        Ok(scene_hash(&description))
+    }
+
+    pub fn render(&mut self) -> Result<Framebuffer> {
+        self.render_progressive(|_, _| Ok(()))
+    }
+
+    /// Renders with `on_pass` called after every pass, see [`Camera::render_progressive`].
+    pub fn render_progressive(
+        &mut self,
+        on_pass: impl FnMut(&PassReport, &Framebuffer) -> Result<()>,
+    ) -> Result<Framebuffer> {
+        self.camera.scene_hash = self.hash()?;
+        self.camera
+            .render_progressive(&self.world, &self.lights, on_pass)
+    }
+}
+
+/// Points the camera at `bbox` from the front and a little above, close enough to see all of it.
+fn frame(camera: &mut Camera, bbox: &AABB) {
+    let min = Point3::new(bbox.x.min, bbox.y.min, bbox.z.min);
+    let max = Point3::new(bbox.x.max, bbox.y.max, bbox.z.max);
+    let center = 0.5 * (min + max);
+    let radius = 0.5 * (max - min).length();
+
+    let distance = radius / (0.5 * camera.vfov.to_radians()).sin();
+    camera.lookat = center;
+    camera.lookfrom = center + distance * Point3::new(0.0, 0.3, 1.0).normalize();
+    camera.vup = Point3::Y;
+}
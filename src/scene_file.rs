@@ -0,0 +1,957 @@
+//! Declarative TOML scene descriptions.
+//!
+//! A scene file has a `[camera]` table, named `[textures.*]` and `[materials.*]` tables that
+//! objects refer to by name, and an `[[objects]]` array of primitives, instances and media.
+//!
+//! ```toml
+//! [camera]
+//! image_width = 400
+//! lookfrom = [0.0, 0.0, 9.0]
+//!
+//! [materials.white]
+//! type = "lambertian"
+//! albedo = [0.73, 0.73, 0.73]
+//!
+//! [[objects]]
+//! type = "sphere"
+//! center = [0.0, 0.0, 0.0]
+//! radius = 1.0
+//! material = "white"
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, Context, Result};
+use glam::Vec3A;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bvh::node::BVHNode,
+    camera::Camera,
+    hittable::{
+        constant_medium::ConstantMedium, hittable_list::HittableList, instance::Transform, new_box,
+        quad::Quad, sphere::Sphere, tagged::Tagged, triangle::Triangle, Hittable,
+    },
+    import,
+    material::*,
+    scene::{Scene, SceneMetadata},
+    texture::{CheckerTexture, NoiseTexture, Texture},
+    util::{color::Color, random, Point3},
+};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneFile {
+    #[serde(default)]
+    pub metadata: SceneMetadata,
+    #[serde(default)]
+    pub camera: CameraDesc,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub textures: BTreeMap<String, TextureDesc>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub materials: BTreeMap<String, MaterialDesc>,
+    /// Objects built once and shared by every instance that names them, see
+    /// [`ObjectDesc::Instance`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub prototypes: BTreeMap<String, ObjectDesc>,
+    #[serde(default)]
+    pub objects: Vec<ObjectDesc>,
+}
+
+/// The user facing settings of a [`Camera`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CameraDesc {
+    pub aspect_ratio: f32,
+    pub image_width: i32,
+    pub samples_per_pixel: i32,
+    pub max_depth: i32,
+    pub background: Color,
+    pub vfov: f32,
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vup: Vec3A,
+    pub defocus_angle: f32,
+    pub focus_dist: f32,
+}
+
+impl Default for CameraDesc {
+    fn default() -> Self {
+        Self::from(&Camera::default())
+    }
+}
+
+impl From<&Camera> for CameraDesc {
+    fn from(camera: &Camera) -> Self {
+        Self {
+            aspect_ratio: camera.aspect_ratio,
+            image_width: camera.image_width,
+            samples_per_pixel: camera.samples_per_pixel,
+            max_depth: camera.max_depth,
+            background: camera.background,
+            vfov: camera.vfov,
+            lookfrom: camera.lookfrom,
+            lookat: camera.lookat,
+            vup: camera.vup,
+            defocus_angle: camera.defocus_angle,
+            focus_dist: camera.focus_dist,
+        }
+    }
+}
+
+impl CameraDesc {
+    pub fn to_camera(&self) -> Camera {
+        let mut camera = Camera::new();
+
+        camera.aspect_ratio = self.aspect_ratio;
+        camera.image_width = self.image_width;
+        camera.samples_per_pixel = self.samples_per_pixel;
+        camera.max_depth = self.max_depth;
+        camera.background = self.background;
+
+        camera.vfov = self.vfov;
+        camera.lookfrom = self.lookfrom;
+        camera.lookat = self.lookat;
+        camera.vup = self.vup;
+
+        camera.defocus_angle = self.defocus_angle;
+        camera.focus_dist = self.focus_dist;
+
+        camera
+    }
+}
+
+/// Either an inline constant color or the name of a texture in `[textures]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TextureRef {
+    Color(Color),
+    Named(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TextureDesc {
+    Solid {
+        color: Color,
+    },
+    Checker {
+        scale: f32,
+        even: TextureRef,
+        odd: TextureRef,
+    },
+    /// An image file, relative to the scene file.
+    Image {
+        path: PathBuf,
+    },
+    Noise {
+        #[serde(default = "one")]
+        scale: f32,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum MaterialDesc {
+    Lambertian {
+        albedo: TextureRef,
+    },
+    Metal {
+        albedo: Color,
+        #[serde(default)]
+        fuzz: f32,
+    },
+    Dielectric {
+        ir: f32,
+    },
+    DiffuseLight {
+        emit: TextureRef,
+    },
+    Isotropic {
+        albedo: TextureRef,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ObjectDesc {
+    Sphere {
+        center: Point3,
+        radius: f32,
+        /// Where the center is at `time = 1`, for motion blur.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        moving_to: Option<Point3>,
+        material: String,
+    },
+    Quad {
+        q: Point3,
+        u: Vec3A,
+        v: Vec3A,
+        material: String,
+    },
+    Triangle {
+        vertices: [Point3; 3],
+        material: String,
+    },
+    /// The triangles of a mesh file relative to the scene file, with the materials of the file
+    /// unless `material` is given.
+    Mesh {
+        path: PathBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        material: Option<String>,
+    },
+    /// A box of six quads spanning the opposite corners `a` and `b`.
+    Box {
+        a: Point3,
+        b: Point3,
+        material: String,
+    },
+    ConstantMedium {
+        boundary: Box<ObjectDesc>,
+        density: f32,
+        albedo: TextureRef,
+    },
+    /// An object, or a prototype shared with other instances, scaled, rotated by degrees about
+    /// the X, Y and Z axes in turn, and then translated. Hits are shaded with `material` instead
+    /// of the materials of the object if it is given, which can't be emissive.
+    ///
+    /// Lights in the object are sampled where the instance puts them, unless `material` overrides
+    /// their emission.
+    Instance {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        object: Option<Box<ObjectDesc>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prototype: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        material: Option<String>,
+        #[serde(default = "ones")]
+        scale: Vec3A,
+        #[serde(default)]
+        rotate_x: f32,
+        #[serde(default)]
+        rotate_y: f32,
+        #[serde(default)]
+        rotate_z: f32,
+        #[serde(default)]
+        translate: Vec3A,
+    },
+    /// A list of objects kept in a BVH of their own.
+    Group { objects: Vec<ObjectDesc> },
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+fn ones() -> Vec3A {
+    Vec3A::ONE
+}
+
+/// Every float in a scene is an `f32`, so print them as one instead of as the widened `f64`.
+fn shorten_floats(value: &mut toml::Value) {
+    match value {
+        toml::Value::Float(f) => *f = (*f as f32).to_string().parse().unwrap_or(*f),
+        toml::Value::Array(array) => array.iter_mut().for_each(shorten_floats),
+        toml::Value::Table(table) => table.iter_mut().for_each(|(_, v)| shorten_floats(v)),
+        _ => {}
+    }
+}
+
+impl SceneFile {
+    pub fn from_toml(src: &str) -> Result<Self> {
+        Ok(toml::from_str(src)?)
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        let mut value = toml::Value::try_from(self)?;
+        shorten_floats(&mut value);
+        Ok(toml::to_string(&value)?)
+    }
+
+    /// Adds a named texture and returns a reference to it.
+    pub fn add_texture(&mut self, name: impl Into<String>, texture: TextureDesc) -> TextureRef {
+        let name = name.into();
+        self.textures.insert(name.clone(), texture);
+        TextureRef::Named(name)
+    }
+
+    /// Adds a named material and returns its name for objects to use.
+    pub fn add_material(&mut self, name: impl Into<String>, material: MaterialDesc) -> String {
+        let name = name.into();
+        self.materials.insert(name.clone(), material);
+        name
+    }
+
+    pub fn add(&mut self, object: ObjectDesc) {
+        self.objects.push(object);
+    }
+
+    /// The image and mesh files the scene refers to, relative to the scene file.
+    pub fn files(&self) -> Vec<&Path> {
+        let mut files = vec![];
+        for texture in self.textures.values() {
+            if let TextureDesc::Image { path } = texture {
+                files.push(path.as_path());
+            }
+        }
+        for object in self.prototypes.values().chain(&self.objects) {
+            object.add_files(&mut files);
+        }
+        files
+    }
+
+    /// Builds the world and its lights, resolving relative paths against `base_dir`.
+    pub fn build(&self, base_dir: &Path) -> Result<(BVHNode, HittableList)> {
+        // Noise textures and the BVH are random, start from the same numbers every time.
+        random::seed(crate::SEED);
+        Builder::new(self, base_dir)
+            .build_world()
+            .map_err(|e| anyhow!("{}: {}", KeyPath(&e.key_path), e.message))
+    }
+}
+
+impl From<Color> for TextureRef {
+    fn from(color: Color) -> Self {
+        TextureRef::Color(color)
+    }
+}
+
+impl MaterialDesc {
+    pub fn lambertian(albedo: impl Into<TextureRef>) -> Self {
+        MaterialDesc::Lambertian {
+            albedo: albedo.into(),
+        }
+    }
+
+    pub fn metal(albedo: Color, fuzz: f32) -> Self {
+        MaterialDesc::Metal { albedo, fuzz }
+    }
+
+    pub fn dielectric(ir: f32) -> Self {
+        MaterialDesc::Dielectric { ir }
+    }
+
+    pub fn diffuse_light(emit: impl Into<TextureRef>) -> Self {
+        MaterialDesc::DiffuseLight { emit: emit.into() }
+    }
+}
+
+impl ObjectDesc {
+    pub fn sphere(center: Point3, radius: f32, material: &str) -> Self {
+        ObjectDesc::Sphere {
+            center,
+            radius,
+            moving_to: None,
+            material: material.to_owned(),
+        }
+    }
+
+    pub fn moving_sphere(center: Point3, endpoint: Point3, radius: f32, material: &str) -> Self {
+        ObjectDesc::Sphere {
+            center,
+            radius,
+            moving_to: Some(endpoint),
+            material: material.to_owned(),
+        }
+    }
+
+    pub fn quad(q: Point3, u: Vec3A, v: Vec3A, material: &str) -> Self {
+        ObjectDesc::Quad {
+            q,
+            u,
+            v,
+            material: material.to_owned(),
+        }
+    }
+
+    pub fn new_box(a: Point3, b: Point3, material: &str) -> Self {
+        ObjectDesc::Box {
+            a,
+            b,
+            material: material.to_owned(),
+        }
+    }
+
+    pub fn constant_medium(
+        boundary: ObjectDesc,
+        density: f32,
+        albedo: impl Into<TextureRef>,
+    ) -> Self {
+        ObjectDesc::ConstantMedium {
+            boundary: Box::new(boundary),
+            density,
+            albedo: albedo.into(),
+        }
+    }
+
+    pub fn instance(object: ObjectDesc, rotate_y: f32, translate: Vec3A) -> Self {
+        ObjectDesc::Instance {
+            object: Some(Box::new(object)),
+            prototype: None,
+            material: None,
+            scale: Vec3A::ONE,
+            rotate_x: 0.0,
+            rotate_y,
+            rotate_z: 0.0,
+            translate,
+        }
+    }
+
+    pub fn group(objects: Vec<ObjectDesc>) -> Self {
+        ObjectDesc::Group { objects }
+    }
+
+    fn add_files<'a>(&'a self, files: &mut Vec<&'a Path>) {
+        match self {
+            ObjectDesc::Mesh { path, .. } => files.push(path),
+            ObjectDesc::ConstantMedium { boundary, .. } => boundary.add_files(files),
+            ObjectDesc::Instance {
+                object: Some(object),
+                ..
+            } => object.add_files(files),
+            ObjectDesc::Group { objects } => objects.iter().for_each(|o| o.add_files(files)),
+            _ => {}
+        }
+    }
+}
+
+/// Loads a scene file and builds its world, lights and camera.
+pub fn load(path: impl AsRef<Path>) -> Result<Scene> {
+    let path = path.as_ref();
+    let src = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read scene file {}", path.display()))?;
+
+    let file: SceneFile = toml::from_str(&src).map_err(|e| {
+        let at = e
+            .span()
+            .map(|span| Position::from_offset(&src, span.start))
+            .unwrap_or_default();
+        anyhow!("{}:{at}: {}", path.display(), e.message())
+    })?;
+
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    random::seed(crate::SEED);
+    let (world, lights) = Builder::new(&file, base_dir).build_world().map_err(|e| {
+        let at = Position::from_key_path(&src, &e.key_path);
+        anyhow!("{}:{at}: {}", path.display(), e.message)
+    })?;
+
+    Ok(Scene::from_parts(file, base_dir, world, lights))
+}
+
+/// One step into a TOML document.
+#[derive(Debug, Clone)]
+enum Key {
+    Name(String),
+    Index(usize),
+}
+
+impl From<&str> for Key {
+    fn from(name: &str) -> Self {
+        Key::Name(name.to_owned())
+    }
+}
+
+impl From<usize> for Key {
+    fn from(index: usize) -> Self {
+        Key::Index(index)
+    }
+}
+
+/// Displays a key path the way it would be written in a TOML document, e.g. `objects[3].material`.
+struct KeyPath<'a>(&'a [Key]);
+
+impl fmt::Display for KeyPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, key) in self.0.iter().enumerate() {
+            match key {
+                Key::Name(name) if i == 0 => write!(f, "{name}")?,
+                Key::Name(name) => write!(f, ".{name}")?,
+                Key::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An error found after parsing, along with the key it is about.
+struct BuildError {
+    key_path: Vec<Key>,
+    message: String,
+}
+
+/// A 1-based line and column into a scene file.
+#[derive(Debug, Clone, Copy)]
+struct Position {
+    line: usize,
+    column: usize,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self { line: 1, column: 1 }
+    }
+}
+
+impl Position {
+    fn from_offset(src: &str, offset: usize) -> Self {
+        let before = &src[..offset.min(src.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+        Self { line, column }
+    }
+
+    /// Finds the closest item to `key_path` that the document knows a position for.
+    fn from_key_path(src: &str, key_path: &[Key]) -> Self {
+        let Ok(doc) = toml_edit::ImDocument::parse(src) else {
+            return Self::default();
+        };
+
+        let mut item = doc.as_item();
+        let mut span = None;
+        for key in key_path {
+            let next = match key {
+                Key::Name(name) => item.get(name.as_str()),
+                Key::Index(index) => item.get(*index),
+            };
+            match next {
+                Some(next) => {
+                    item = next;
+                    span = item.span().or(span);
+                }
+                None => break,
+            }
+        }
+
+        span.map(|span| Self::from_offset(src, span.start))
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// A prototype that has been built, with its emissive primitives where the prototype is.
+#[derive(Clone)]
+struct Prototype {
+    object: Arc<dyn Hittable>,
+    lights: Vec<Arc<dyn Hittable>>,
+}
+
+/// Turns the descriptions of a [`SceneFile`] into hittables, sharing named textures and materials.
+struct Builder<'a> {
+    file: &'a SceneFile,
+    base_dir: &'a Path,
+    key_path: Vec<Key>,
+    textures: HashMap<String, Arc<dyn Texture>>,
+    materials: HashMap<String, Material>,
+    /// Emissive primitives, which are also added to the world.
+    lights: HittableList,
+    /// Textures currently being built, to catch ones that refer to themselves.
+    textures_in_progress: Vec<String>,
+    prototypes: HashMap<String, Prototype>,
+    /// Prototypes currently being built, to catch ones that instance themselves.
+    prototypes_in_progress: Vec<String>,
+    /// Id of the top level object being built, see [`Tagged`].
+    object_id: u32,
+}
+
+impl<'a> Builder<'a> {
+    fn new(file: &'a SceneFile, base_dir: &'a Path) -> Self {
+        Self {
+            file,
+            base_dir,
+            key_path: vec![],
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+            lights: HittableList::new(),
+            textures_in_progress: vec![],
+            prototypes: HashMap::new(),
+            prototypes_in_progress: vec![],
+            object_id: 0,
+        }
+    }
+
+    fn error(&self, key: impl Into<Key>, message: impl fmt::Display) -> BuildError {
+        let mut key_path = self.key_path.clone();
+        key_path.push(key.into());
+        BuildError {
+            key_path,
+            message: message.to_string(),
+        }
+    }
+
+    fn error_here(&self, message: impl fmt::Display) -> BuildError {
+        BuildError {
+            key_path: self.key_path.clone(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Runs `f` with `key` appended to the current key path.
+    fn nested<T>(
+        &mut self,
+        key: impl Into<Key>,
+        f: impl FnOnce(&mut Self) -> Result<T, BuildError>,
+    ) -> Result<T, BuildError> {
+        self.key_path.push(key.into());
+        let out = f(self);
+        self.key_path.pop();
+        out
+    }
+
+    fn build_world(mut self) -> Result<(BVHNode, HittableList), BuildError> {
+        if self.file.objects.is_empty() {
+            return Err(self.error("objects", "the scene has no objects"));
+        }
+
+        let file = self.file;
+        let world = self.nested("objects", |b| {
+            let mut list = HittableList::new();
+            for (i, object) in file.objects.iter().enumerate() {
+                // Everything a top level object is made of gets its id.
+                b.object_id = i as u32 + 1;
+                list.add_shared(b.nested(i, |b| b.build_object(object))?);
+            }
+            Ok(list)
+        })?;
+        Ok((BVHNode::from_list(&world), self.lights))
+    }
+
+    fn build_list(&mut self, objects: &[ObjectDesc]) -> Result<HittableList, BuildError> {
+        let mut list = HittableList::new();
+        for (i, object) in objects.iter().enumerate() {
+            let object = self.nested(i, |b| b.build_object(object))?;
+            list.add_shared(object);
+        }
+        Ok(list)
+    }
+
+    fn build_object(&mut self, object: &ObjectDesc) -> Result<Arc<dyn Hittable>, BuildError> {
+        Ok(match object {
+            ObjectDesc::Sphere {
+                center,
+                radius,
+                moving_to,
+                material,
+            } => {
+                let material_id = self.material_id(material);
+                let material = self.material(material)?;
+                let is_light = matches!(material, Material::DiffuseLight(_));
+                let sphere = match moving_to {
+                    Some(endpoint) => Sphere::new_moving(*center, *endpoint, *radius, material),
+                    None => Sphere::new_stationary(*center, *radius, material),
+                };
+                let sphere = self.tag(sphere, material_id);
+                if is_light {
+                    self.lights.add_shared(sphere.clone());
+                }
+                sphere
+            }
+            ObjectDesc::Quad { q, u, v, material } => {
+                let material_id = self.material_id(material);
+                let material = self.material(material)?;
+                let is_light = matches!(material, Material::DiffuseLight(_));
+                let quad = self.tag(Quad::new(*q, *u, *v, material), material_id);
+                if is_light {
+                    self.lights.add_shared(quad.clone());
+                }
+                quad
+            }
+            ObjectDesc::Triangle { vertices, material } => {
+                let material_id = self.material_id(material);
+                let material = self.material(material)?;
+                let is_light = matches!(material, Material::DiffuseLight(_));
+                let [a, b, c] = *vertices;
+                let triangle = self.tag(Triangle::new(a, b, c, material), material_id);
+                if is_light {
+                    self.lights.add_shared(triangle.clone());
+                }
+                triangle
+            }
+            ObjectDesc::Mesh { path, material } => {
+                let full_path = self.base_dir.join(path);
+                let meshes = import::load_meshes(&full_path)
+                    .map_err(|e| self.error("path", format!("{e:#}")))?;
+                let (material, material_id) = match material {
+                    Some(name) => (Some(self.material(name)?), self.material_id(name)),
+                    None => (None, 0),
+                };
+
+                let mut list = HittableList::new();
+                for mut mesh in meshes {
+                    if let Some(material) = &material {
+                        mesh.materials = vec![material.clone()];
+                        mesh.face_materials.clear();
+                    }
+                    let mesh = Arc::new(mesh);
+                    for triangle in mesh.triangles().objects {
+                        list.add_shared(triangle);
+                    }
+                    for light in mesh.lights().objects {
+                        self.lights.add_shared(self.tag(light, material_id));
+                    }
+                }
+                if list.is_empty() {
+                    return Err(self.error("path", "the mesh has no triangles"));
+                }
+                self.tag(BVHNode::from_list(&list), material_id)
+            }
+            ObjectDesc::Box { a, b, material } => {
+                let material_id = self.material_id(material);
+                let material = self.material(material)?;
+                let is_light = matches!(material, Material::DiffuseLight(_));
+                let sides = self.tag(new_box(*a, *b, material), material_id);
+                if is_light {
+                    self.lights.add_shared(sides.clone());
+                }
+                sides
+            }
+            ObjectDesc::ConstantMedium {
+                boundary,
+                density,
+                albedo,
+            } => {
+                let (boundary, lights) =
+                    self.own_lights(|b| b.nested("boundary", |b| b.build_object(boundary)));
+                let boundary = boundary?;
+                if !lights.is_empty() {
+                    return Err(self.error(
+                        "boundary",
+                        "media don't emit light, the boundary only gives the medium its shape",
+                    ));
+                }
+                let albedo = self.nested("albedo", |b| b.texture_ref(albedo))?;
+                self.tag(ConstantMedium::new(boundary, *density, albedo), 0)
+            }
+            ObjectDesc::Instance {
+                object,
+                prototype,
+                material,
+                scale,
+                rotate_x,
+                rotate_y,
+                rotate_z,
+                translate,
+            } => {
+                if scale.cmpeq(Vec3A::ZERO).any() {
+                    return Err(self.error("scale", "an instance can't be scaled to zero"));
+                }
+                let (object, lights) = match (object, prototype) {
+                    (Some(object), None) => {
+                        let (object, lights) =
+                            self.own_lights(|b| b.nested("object", |b| b.build_object(object)));
+                        (object?, lights)
+                    }
+                    (None, Some(name)) => {
+                        let prototype = self.nested("prototype", |b| b.prototype(name))?;
+                        (prototype.object, prototype.lights)
+                    }
+                    _ => {
+                        return Err(
+                            self.error_here("an instance needs either an object or a prototype")
+                        )
+                    }
+                };
+                let place = |object| {
+                    Transform::shared(object)
+                        .scale(*scale)
+                        .rotate(Vec3A::X, *rotate_x)
+                        .rotate(Vec3A::Y, *rotate_y)
+                        .rotate(Vec3A::Z, *rotate_z)
+                        .translate(*translate)
+                };
+                let mut transform = place(object);
+                let material_id = match material {
+                    Some(name) => {
+                        let material = self.material(name)?;
+                        if matches!(material, Material::DiffuseLight(_)) {
+                            return Err(self.error(
+                                "material",
+                                "an instance can't be made a light, give the object an emissive \
+                                 material instead",
+                            ));
+                        }
+                        transform = transform.with_material(material);
+                        self.material_id(name)
+                    }
+                    None => {
+                        // The lights of the object are sampled where the instance puts them. With
+                        // a material of its own they may not be lights anymore, and are only hit.
+                        for light in lights {
+                            self.lights.add(place(light));
+                        }
+                        0
+                    }
+                };
+                // Prototypes are built without an object id, so every instance gets its own.
+                self.tag(transform, material_id)
+            }
+            ObjectDesc::Group { objects } => {
+                let list = self.nested("objects", |b| b.build_list(objects))?;
+                if list.objects.is_empty() {
+                    return Err(self.error("objects", "a group needs at least one object"));
+                }
+                Arc::new(BVHNode::from_list(&list))
+            }
+        })
+    }
+
+    fn prototype(&mut self, name: &str) -> Result<Prototype, BuildError> {
+        if let Some(prototype) = self.prototypes.get(name) {
+            return Ok(prototype.clone());
+        }
+
+        let file = self.file;
+        let Some(desc) = file.prototypes.get(name) else {
+            return Err(self.error_here(format!("unknown prototype `{name}`")));
+        };
+        if self.prototypes_in_progress.iter().any(|n| n == name) {
+            return Err(self.error_here(format!("prototype `{name}` instances itself")));
+        }
+
+        self.prototypes_in_progress.push(name.to_owned());
+        let saved_path = std::mem::replace(
+            &mut self.key_path,
+            vec![Key::from("prototypes"), Key::from(name)],
+        );
+        let saved_object_id = std::mem::replace(&mut self.object_id, 0);
+        let (prototype, lights) = self.own_lights(|b| b.build_object(desc));
+        self.object_id = saved_object_id;
+        self.key_path = saved_path;
+        self.prototypes_in_progress.pop();
+
+        let prototype = Prototype {
+            object: prototype?,
+            lights,
+        };
+        self.prototypes.insert(name.to_owned(), prototype.clone());
+        Ok(prototype)
+    }
+
+    /// Runs `build`, keeping the lights it adds out of the scene's, as they aren't where the
+    /// instances of what it builds put them.
+    fn own_lights<T>(&mut self, build: impl FnOnce(&mut Self) -> T) -> (T, Vec<Arc<dyn Hittable>>) {
+        let saved_lights = std::mem::take(&mut self.lights);
+        let built = build(self);
+        let lights = std::mem::replace(&mut self.lights, saved_lights);
+        (built, lights.objects)
+    }
+
+    /// Marks the hits of a primitive with the current object id and the id of its material.
+    fn tag(&self, object: impl Hittable + 'static, material_id: u32) -> Arc<dyn Hittable> {
+        Arc::new(Tagged::new(object, self.object_id, material_id))
+    }
+
+    /// 1 + the index of the material among the materials of the file, 0 if there is none by
+    /// that name.
+    fn material_id(&self, name: &str) -> u32 {
+        self.file
+            .materials
+            .keys()
+            .position(|key| key == name)
+            .map_or(0, |i| i as u32 + 1)
+    }
+
+    fn material(&mut self, name: &str) -> Result<Material, BuildError> {
+        if let Some(material) = self.materials.get(name) {
+            return Ok(material.clone());
+        }
+
+        let file = self.file;
+        let Some(desc) = file.materials.get(name) else {
+            return Err(self.error("material", format!("unknown material `{name}`")));
+        };
+
+        let saved_path = std::mem::replace(
+            &mut self.key_path,
+            vec![Key::from("materials"), Key::from(name)],
+        );
+        let material = self.build_material(desc);
+        self.key_path = saved_path;
+
+        let material = material?;
+        self.materials.insert(name.to_owned(), material.clone());
+        Ok(material)
+    }
+
+    fn build_material(&mut self, desc: &MaterialDesc) -> Result<Material, BuildError> {
+        Ok(match desc {
+            MaterialDesc::Lambertian { albedo } => {
+                Lambertian::new(self.nested("albedo", |b| b.texture_ref(albedo))?)
+            }
+            MaterialDesc::Metal { albedo, fuzz } => Metal::new(*albedo, *fuzz),
+            MaterialDesc::Dielectric { ir } => Dielectric::new(*ir),
+            MaterialDesc::DiffuseLight { emit } => {
+                DiffuseLight::new(self.nested("emit", |b| b.texture_ref(emit))?)
+            }
+            MaterialDesc::Isotropic { albedo } => {
+                Isotropic::new(self.nested("albedo", |b| b.texture_ref(albedo))?)
+            }
+        })
+    }
+
+    fn texture_ref(&mut self, texture: &TextureRef) -> Result<Arc<dyn Texture>, BuildError> {
+        match texture {
+            TextureRef::Color(color) => Ok(Arc::new(*color)),
+            TextureRef::Named(name) => self.texture(name),
+        }
+    }
+
+    fn texture(&mut self, name: &str) -> Result<Arc<dyn Texture>, BuildError> {
+        if let Some(texture) = self.textures.get(name) {
+            return Ok(texture.clone());
+        }
+
+        let file = self.file;
+        let Some(desc) = file.textures.get(name) else {
+            return Err(self.error_here(format!("unknown texture `{name}`")));
+        };
+        if self.textures_in_progress.iter().any(|n| n == name) {
+            return Err(self.error_here(format!("texture `{name}` refers to itself")));
+        }
+
+        self.textures_in_progress.push(name.to_owned());
+        let saved_path = std::mem::replace(
+            &mut self.key_path,
+            vec![Key::from("textures"), Key::from(name)],
+        );
+        let texture = self.build_texture(desc);
+        self.key_path = saved_path;
+        self.textures_in_progress.pop();
+
+        let texture = texture?;
+        self.textures.insert(name.to_owned(), texture.clone());
+        Ok(texture)
+    }
+
+    fn build_texture(&mut self, desc: &TextureDesc) -> Result<Arc<dyn Texture>, BuildError> {
+        Ok(match desc {
+            TextureDesc::Solid { color } => Arc::new(*color),
+            TextureDesc::Checker { scale, even, odd } => {
+                let even = self.nested("even", |b| b.texture_ref(even))?;
+                let odd = self.nested("odd", |b| b.texture_ref(odd))?;
+                Arc::new(CheckerTexture::new(*scale, even, odd))
+            }
+            TextureDesc::Image { path } => {
+                let full_path = self.base_dir.join(path);
+                let image = image::open(&full_path).map_err(|e| {
+                    self.error(
+                        "path",
+                        format!("failed to open image {}: {e}", full_path.display()),
+                    )
+                })?;
+                Arc::new(image)
+            }
+            TextureDesc::Noise { scale } => Arc::new(NoiseTexture::scaled(*scale)),
+        })
+    }
+}
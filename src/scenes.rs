@@ -2,486 +2,667 @@ use anyhow::Result;
 use glam::Vec3A;
 
 use crate::{
-    bvh::node::BVHNode,
-    camera::Camera,
-    hittable::{
-        constant_medium::ConstantMedium,
-        hittable_list::HittableList,
-        instance::{Rotation, Translate},
-        new_box,
-        quad::Quad,
-        sphere::Sphere,
-    },
-    material::*,
-    texture::{CheckerTexture, NoiseTexture},
+    camera::CameraOverrides,
+    scene::{Scene, SceneMetadata},
+    scene_file::{CameraDesc, MaterialDesc, ObjectDesc, SceneFile, TextureDesc},
     util::{
         color::Color,
-        random::{random_f32, random_vec_in_range},
-        vec::AXIS_Y,
+        random::{self, random_f32, random_vec_in_range},
         Point3,
     },
 };
 
-pub fn random_spheres() -> Result<()> {
-    let mut world = HittableList::new();
+/// Builds a built-in scene.
+pub type SceneBuilder = fn() -> Result<Scene>;
+
+/// Every built-in scene by name, in the order they appear in this file.
+pub const SCENES: &[(&str, SceneBuilder)] = &[
+    ("random_spheres", build_random_spheres),
+    ("two_spheres", build_two_spheres),
+    ("earth", build_earth),
+    ("two_perlin_spheres", build_two_perlin_spheres),
+    ("quads", build_quads),
+    ("simple_light", build_simple_light),
+    ("cornell_box", build_cornell_box),
+    ("cornell_smoke", build_cornell_smoke),
+    (
+        "next_weeks_final_scene_low_res",
+        build_next_weeks_final_scene_low_res,
+    ),
+    (
+        "next_weeks_final_scene_mid_res",
+        build_next_weeks_final_scene_mid_res,
+    ),
+    (
+        "next_weeks_final_scene_high_res",
+        build_next_weeks_final_scene_high_res,
+    ),
+];
+
+/// Looks up a built-in scene by its function name.
+pub fn find(name: &str) -> Option<SceneBuilder> {
+    SCENES
+        .iter()
+        .find(|(scene_name, _)| *scene_name == name)
+        .map(|(_, scene)| *scene)
+}
+
+/// Renders a scene after applying the camera overrides and writes it to `image.jpg`.
+fn render(mut scene: Scene, overrides: &CameraOverrides) -> Result<()> {
+    overrides.apply(&mut scene.camera);
+    scene.render()?.save("image.jpg")
+}
+
+fn metadata(name: &str, description: &str) -> SceneMetadata {
+    SceneMetadata {
+        name: name.to_owned(),
+        description: description.to_owned(),
+    }
+}
 
-    let ground_material = Lambertian::new(CheckerTexture::new(1.0, Color::ONE, Color::ZERO));
+pub fn build_random_spheres() -> Result<Scene> {
+    random::seed(crate::SEED);
+    let mut file = SceneFile {
+        metadata: metadata(
+            "random_spheres",
+            "The final scene of Ray Tracing in One Weekend, with bouncing spheres.",
+        ),
+        ..Default::default()
+    };
+
+    let checker = file.add_texture(
+        "checker",
+        TextureDesc::Checker {
+            scale: 1.0,
+            even: Color::ONE.into(),
+            odd: Color::ZERO.into(),
+        },
+    );
+    let ground_material = file.add_material("ground", MaterialDesc::lambertian(checker));
 
-    world.add(Sphere::new_stationary(
+    file.add(ObjectDesc::sphere(
         Point3::new(0.0, -1000.0, 0.0),
         1000.0,
-        ground_material,
+        &ground_material,
     ));
 
     for a in -11..11 {
         for b in -11..11 {
-            let choose_mat = rand::random::<f32>();
+            let choose_mat = random_f32(0.0, 1.0);
             let center = Point3::new(
-                a as f32 + 0.9 * rand::random::<f32>(),
+                a as f32 + 0.9 * random_f32(0.0, 1.0),
                 0.2,
-                b as f32 + 0.9 * rand::random::<f32>(),
+                b as f32 + 0.9 * random_f32(0.0, 1.0),
             );
 
             if (center - Point3::new(4.0, 0.2, 0.0)).length() > 0.9 {
                 let material = if choose_mat < 0.8 {
                     // Diffuse
                     let albedo = random_vec_in_range(0.0, 1.0) * random_vec_in_range(0.0, 1.0);
-                    Lambertian::new(albedo)
+                    MaterialDesc::lambertian(albedo)
                 } else if choose_mat < 0.95 {
                     // Metal
                     let albedo = random_vec_in_range(0.5, 1.0);
                     let fuzz = random_f32(0.0, 0.5);
-                    Metal::new(albedo, fuzz)
+                    MaterialDesc::metal(albedo, fuzz)
                 } else {
                     // Glass
-                    Dielectric::new(1.5)
+                    MaterialDesc::dielectric(1.5)
                 };
+                let material = file.add_material(format!("sphere_{a}_{b}"), material);
 
                 if choose_mat < 0.8 {
-                    world.add(Sphere::new_moving(
+                    file.add(ObjectDesc::moving_sphere(
                         center,
-                        center + Vec3A::new(0.0, rand::random::<f32>() / 2.0, 0.0),
+                        center + Vec3A::new(0.0, random_f32(0.0, 1.0) / 2.0, 0.0),
                         0.2,
-                        material,
+                        &material,
                     ));
                 } else {
-                    world.add(Sphere::new_stationary(center, 0.2, material));
+                    file.add(ObjectDesc::sphere(center, 0.2, &material));
                 }
             }
         }
     }
 
-    let material1 = Dielectric::new(1.5);
-    world.add(Sphere::new_stationary(
+    let material1 = file.add_material("material1", MaterialDesc::dielectric(1.5));
+    file.add(ObjectDesc::sphere(
         Point3::new(0.0, 1.0, 0.0),
         1.0,
-        material1,
+        &material1,
     ));
 
-    let material2 = Lambertian::new(Color::new(0.4, 0.2, 0.1));
-    world.add(Sphere::new_stationary(
+    let material2 = file.add_material(
+        "material2",
+        MaterialDesc::lambertian(Color::new(0.4, 0.2, 0.1)),
+    );
+    file.add(ObjectDesc::sphere(
         Point3::new(-4.0, 1.0, 0.0),
         1.0,
-        material2,
+        &material2,
     ));
 
-    let material3 = Metal::new(Color::new(0.7, 0.6, 0.5), 0.0);
-    world.add(Sphere::new_stationary(
+    let material3 = file.add_material(
+        "material3",
+        MaterialDesc::metal(Color::new(0.7, 0.6, 0.5), 0.0),
+    );
+    file.add(ObjectDesc::sphere(
         Point3::new(4.0, 1.0, 0.0),
         1.0,
-        material3,
+        &material3,
     ));
 
-    let world = BVHNode::from_list(&world);
-
     // Camera
-    let mut camera = Camera::new();
-
-    camera.aspect_ratio = 16.0 / 9.0;
-    camera.image_width = 1200;
-    camera.samples_per_pixel = 100;
-    camera.max_depth = 50;
-    camera.background = Color::new(0.70, 0.80, 1.00);
-
-    camera.vfov = 20.0;
-    camera.lookfrom = Point3::new(13.0, 2.0, 3.0);
-    camera.lookat = Point3::new(0.0, 0.0, 0.0);
-
-    camera.defocus_angle = 0.6;
-    camera.focus_dist = 10.0;
-
-    // Render
-    camera.render(&world)
+    file.camera = CameraDesc {
+        aspect_ratio: 16.0 / 9.0,
+        image_width: 1200,
+        samples_per_pixel: 100,
+        max_depth: 50,
+        background: Color::new(0.70, 0.80, 1.00),
+
+        vfov: 20.0,
+        lookfrom: Point3::new(13.0, 2.0, 3.0),
+        lookat: Point3::new(0.0, 0.0, 0.0),
+        vup: Vec3A::Y,
+
+        defocus_angle: 0.6,
+        focus_dist: 10.0,
+    };
+
+    Scene::new(file)
 }
 
-pub fn two_spheres() -> Result<()> {
-    let mut world = HittableList::new();
+pub fn random_spheres(overrides: &CameraOverrides) -> Result<()> {
+    render(build_random_spheres()?, overrides)
+}
 
-    let checker = Lambertian::new(CheckerTexture::new(
-        0.8,
-        Color::new(0.2, 0.3, 0.1),
-        Color::new(0.9, 0.9, 0.9),
-    ));
+pub fn build_two_spheres() -> Result<Scene> {
+    let mut file = SceneFile {
+        metadata: metadata("two_spheres", "Two checkered spheres touching."),
+        ..Default::default()
+    };
+
+    let checker = file.add_texture(
+        "checker",
+        TextureDesc::Checker {
+            scale: 0.8,
+            even: Color::new(0.2, 0.3, 0.1).into(),
+            odd: Color::new(0.9, 0.9, 0.9).into(),
+        },
+    );
+    let checker = file.add_material("checker", MaterialDesc::lambertian(checker));
 
-    world.add(Sphere::new_stationary(
+    file.add(ObjectDesc::sphere(
         Point3::new(0.0, -10.0, 0.0),
         10.0,
-        checker.clone(),
+        &checker,
     ));
-    world.add(Sphere::new_stationary(
+    file.add(ObjectDesc::sphere(
         Point3::new(0.0, 10.0, 0.0),
         10.0,
-        checker,
+        &checker,
     ));
 
-    let mut camera = Camera::new();
+    file.camera = CameraDesc {
+        aspect_ratio: 16.0 / 9.0,
+        image_width: 400,
+        samples_per_pixel: 100,
+        max_depth: 50,
+        background: Color::new(0.70, 0.80, 1.00),
 
-    camera.aspect_ratio = 16.0 / 9.0;
-    camera.image_width = 400;
-    camera.samples_per_pixel = 100;
-    camera.max_depth = 50;
-    camera.background = Color::new(0.70, 0.80, 1.00);
+        vfov: 20.0,
+        lookfrom: Point3::new(13.0, 2.0, 3.0),
+        lookat: Point3::ZERO,
+        vup: Vec3A::Y,
 
-    camera.vfov = 20.0;
-    camera.lookfrom = Point3::new(13.0, 2.0, 3.0);
-    camera.lookat = Point3::ZERO;
-    camera.vup = Vec3A::Y;
+        defocus_angle: 0.0,
+        ..Default::default()
+    };
 
-    camera.defocus_angle = 0.0;
-
-    camera.render(&world)
+    Scene::new(file)
 }
 
-pub fn earth() -> Result<()> {
-    let earth_texture = Lambertian::new(image::open("assets/textures/earthmap.jpg").unwrap());
-    let globe = Sphere::new_stationary(Point3::ZERO, 2.0, earth_texture);
-
-    let mut camera = Camera::new();
-
-    camera.aspect_ratio = 16.0 / 9.0;
-    camera.image_width = 400;
-    camera.samples_per_pixel = 100;
-    camera.max_depth = 50;
-    camera.background = Color::new(0.70, 0.80, 1.00);
-
-    camera.vfov = 20.0;
-    camera.lookfrom = Point3::new(0.0, 0.0, 12.0);
-    camera.lookat = Point3::ZERO;
-    camera.vup = Vec3A::Y;
-
-    camera.defocus_angle = 0.0;
+pub fn two_spheres(overrides: &CameraOverrides) -> Result<()> {
+    render(build_two_spheres()?, overrides)
+}
 
-    camera.render(&HittableList::new_with(globe))
+pub fn build_earth() -> Result<Scene> {
+    let mut file = SceneFile {
+        metadata: metadata("earth", "An image textured globe."),
+        ..Default::default()
+    };
+
+    let earth_texture = file.add_texture(
+        "earthmap",
+        TextureDesc::Image {
+            path: "assets/textures/earthmap.jpg".into(),
+        },
+    );
+    let earth_surface = file.add_material("earth", MaterialDesc::lambertian(earth_texture));
+    file.add(ObjectDesc::sphere(Point3::ZERO, 2.0, &earth_surface));
+
+    file.camera = CameraDesc {
+        aspect_ratio: 16.0 / 9.0,
+        image_width: 400,
+        samples_per_pixel: 100,
+        max_depth: 50,
+        background: Color::new(0.70, 0.80, 1.00),
+
+        vfov: 20.0,
+        lookfrom: Point3::new(0.0, 0.0, 12.0),
+        lookat: Point3::ZERO,
+        vup: Vec3A::Y,
+
+        defocus_angle: 0.0,
+        ..Default::default()
+    };
+
+    Scene::new(file)
 }
 
-pub fn two_perlin_spheres() -> Result<()> {
-    let mut world = HittableList::new();
+pub fn earth(overrides: &CameraOverrides) -> Result<()> {
+    render(build_earth()?, overrides)
+}
 
-    let noise_texture = Lambertian::new(NoiseTexture::scaled(4.0));
-    world.add(Sphere::new_stationary(
+pub fn build_two_perlin_spheres() -> Result<Scene> {
+    let mut file = SceneFile {
+        metadata: metadata(
+            "two_perlin_spheres",
+            "Two spheres with Perlin noise marble.",
+        ),
+        ..Default::default()
+    };
+
+    let noise_texture = file.add_texture("noise", TextureDesc::Noise { scale: 4.0 });
+    let noise_texture = file.add_material("noise", MaterialDesc::lambertian(noise_texture));
+    file.add(ObjectDesc::sphere(
         Point3::new(0.0, -1000.0, 0.0),
         1000.0,
-        noise_texture.clone(),
+        &noise_texture,
     ));
-    world.add(Sphere::new_stationary(
+    file.add(ObjectDesc::sphere(
         Point3::new(0.0, 2.0, 0.0),
         2.0,
-        noise_texture.clone(),
+        &noise_texture,
     ));
 
-    let mut camera = Camera::new();
+    file.camera = CameraDesc {
+        aspect_ratio: 16.0 / 9.0,
+        image_width: 400,
+        samples_per_pixel: 100,
+        max_depth: 50,
+        background: Color::new(0.70, 0.80, 1.00),
 
-    camera.aspect_ratio = 16.0 / 9.0;
-    camera.image_width = 400;
-    camera.samples_per_pixel = 100;
-    camera.max_depth = 50;
-    camera.background = Color::new(0.70, 0.80, 1.00);
+        vfov: 20.0,
+        lookfrom: Point3::new(13.0, 2.0, 3.0),
+        lookat: Point3::ZERO,
+        vup: Vec3A::Y,
 
-    camera.vfov = 20.0;
-    camera.lookfrom = Point3::new(13.0, 2.0, 3.0);
-    camera.lookat = Point3::ZERO;
-    camera.vup = Vec3A::Y;
+        defocus_angle: 0.0,
+        ..Default::default()
+    };
 
-    camera.defocus_angle = 0.0;
+    Scene::new(file)
+}
 
-    camera.render(&world)
+pub fn two_perlin_spheres(overrides: &CameraOverrides) -> Result<()> {
+    render(build_two_perlin_spheres()?, overrides)
 }
 
-pub fn quads() -> Result<()> {
-    let mut world = HittableList::new();
+pub fn build_quads() -> Result<Scene> {
+    let mut file = SceneFile {
+        metadata: metadata("quads", "Five colored quads facing the camera."),
+        ..Default::default()
+    };
 
-    let left_red = Lambertian::new(Color::new(1.0, 0.2, 0.2));
-    let back_green = Lambertian::new(Color::new(0.2, 1.0, 0.2));
-    let right_blue = Lambertian::new(Color::new(0.2, 0.2, 1.0));
-    let upper_orange = Lambertian::new(Color::new(1.0, 0.5, 0.0));
-    let lower_teal = Lambertian::new(Color::new(0.2, 0.8, 0.8));
+    let left_red = file.add_material(
+        "left_red",
+        MaterialDesc::lambertian(Color::new(1.0, 0.2, 0.2)),
+    );
+    let back_green = file.add_material(
+        "back_green",
+        MaterialDesc::lambertian(Color::new(0.2, 1.0, 0.2)),
+    );
+    let right_blue = file.add_material(
+        "right_blue",
+        MaterialDesc::lambertian(Color::new(0.2, 0.2, 1.0)),
+    );
+    let upper_orange = file.add_material(
+        "upper_orange",
+        MaterialDesc::lambertian(Color::new(1.0, 0.5, 0.0)),
+    );
+    let lower_teal = file.add_material(
+        "lower_teal",
+        MaterialDesc::lambertian(Color::new(0.2, 0.8, 0.8)),
+    );
 
-    world.add(Quad::new(
+    file.add(ObjectDesc::quad(
         Point3::new(-3.0, -2.0, 5.0),
         Vec3A::Z * -4.0,
         Vec3A::Y * 4.0,
-        left_red,
+        &left_red,
     ));
-    world.add(Quad::new(
+    file.add(ObjectDesc::quad(
         Point3::new(-2.0, -2.0, 0.0),
         Vec3A::X * 4.0,
         Vec3A::Y * 4.0,
-        back_green,
+        &back_green,
     ));
-    world.add(Quad::new(
+    file.add(ObjectDesc::quad(
         Point3::new(3.0, -2.0, 1.0),
         Vec3A::Z * 4.0,
         Vec3A::Y * 4.0,
-        right_blue,
+        &right_blue,
     ));
-    world.add(Quad::new(
+    file.add(ObjectDesc::quad(
         Point3::new(-2.0, 3.0, 1.0),
         Vec3A::X * 4.0,
         Vec3A::Z * 4.0,
-        upper_orange,
+        &upper_orange,
     ));
-    world.add(Quad::new(
+    file.add(ObjectDesc::quad(
         Point3::new(-2.0, -3.0, 5.0),
         Vec3A::X * 4.0,
         Vec3A::Z * -4.0,
-        lower_teal,
+        &lower_teal,
     ));
 
-    let mut camera = Camera::new();
-
-    camera.aspect_ratio = 1.0;
-    camera.image_width = 400;
-    camera.samples_per_pixel = 100;
-    camera.max_depth = 50;
-    camera.background = Color::new(0.70, 0.80, 1.00);
+    file.camera = CameraDesc {
+        aspect_ratio: 1.0,
+        image_width: 400,
+        samples_per_pixel: 100,
+        max_depth: 50,
+        background: Color::new(0.70, 0.80, 1.00),
 
-    camera.vfov = 80.0;
-    camera.lookfrom = Point3::new(0.0, 0.0, 9.0);
-    camera.lookat = Point3::ZERO;
-    camera.vup = Vec3A::Y;
+        vfov: 80.0,
+        lookfrom: Point3::new(0.0, 0.0, 9.0),
+        lookat: Point3::ZERO,
+        vup: Vec3A::Y,
 
-    camera.defocus_angle = 0.0;
+        defocus_angle: 0.0,
+        ..Default::default()
+    };
 
-    camera.render(&world)
+    Scene::new(file)
 }
 
-pub fn simple_light() -> Result<()> {
-    let mut world = HittableList::new();
+pub fn quads(overrides: &CameraOverrides) -> Result<()> {
+    render(build_quads()?, overrides)
+}
 
-    let noise_texture = Lambertian::new(NoiseTexture::scaled(4.0));
-    world.add(Sphere::new_stationary(
+pub fn build_simple_light() -> Result<Scene> {
+    let mut file = SceneFile {
+        metadata: metadata(
+            "simple_light",
+            "Perlin spheres lit by an emissive sphere and quad.",
+        ),
+        ..Default::default()
+    };
+
+    let noise_texture = file.add_texture("noise", TextureDesc::Noise { scale: 4.0 });
+    let noise_texture = file.add_material("noise", MaterialDesc::lambertian(noise_texture));
+    file.add(ObjectDesc::sphere(
         Point3::new(0.0, -1000.0, 0.0),
         1000.0,
-        noise_texture.clone(),
+        &noise_texture,
     ));
-    world.add(Sphere::new_stationary(
+    file.add(ObjectDesc::sphere(
         Point3::new(0.0, 2.0, 0.0),
         2.0,
-        noise_texture.clone(),
+        &noise_texture,
     ));
 
-    let diff_light = DiffuseLight::new(Color::new(8.0, 8.0, 8.0));
-    world.add(Sphere::new_stationary(
+    let diff_light = file.add_material(
+        "light",
+        MaterialDesc::diffuse_light(Color::new(8.0, 8.0, 8.0)),
+    );
+    file.add(ObjectDesc::sphere(
         Point3::new(0.0, 7.0, 0.0),
         1.0,
-        diff_light.clone(),
+        &diff_light,
     ));
-    world.add(Quad::new(
+    file.add(ObjectDesc::quad(
         Point3::new(3.0, 1.0, -2.0),
         Vec3A::X * 2.0,
         Vec3A::Y * 2.0,
-        diff_light,
+        &diff_light,
     ));
 
-    let mut camera = Camera::new();
+    file.camera = CameraDesc {
+        aspect_ratio: 16.0 / 9.0,
+        image_width: 400,
+        samples_per_pixel: 100,
+        max_depth: 50,
+        background: Color::new(0.0, 0.0, 0.0),
 
-    camera.aspect_ratio = 16.0 / 9.0;
-    camera.image_width = 400;
-    camera.samples_per_pixel = 100;
-    camera.max_depth = 50;
-    camera.background = Color::new(0.0, 0.0, 0.0);
+        vfov: 20.0,
+        lookfrom: Point3::new(26.0, 3.0, 6.0),
+        lookat: Point3::Y * 2.0,
+        vup: Vec3A::Y,
 
-    camera.vfov = 20.0;
-    camera.lookfrom = Point3::new(26.0, 3.0, 6.0);
-    camera.lookat = Point3::Y * 2.0;
-    camera.vup = Vec3A::Y;
+        defocus_angle: 0.0,
+        ..Default::default()
+    };
 
-    camera.defocus_angle = 0.0;
+    Scene::new(file)
+}
 
-    camera.render(&world)
+pub fn simple_light(overrides: &CameraOverrides) -> Result<()> {
+    render(build_simple_light()?, overrides)
 }
 
-pub fn cornell_box() -> Result<()> {
-    let mut world = HittableList::new();
+pub fn build_cornell_box() -> Result<Scene> {
+    let mut file = SceneFile {
+        metadata: metadata("cornell_box", "The Cornell box with two rotated boxes."),
+        ..Default::default()
+    };
 
-    let red = Lambertian::new(Color::new(0.65, 0.05, 0.05));
-    let white = Lambertian::new(Color::new(0.73, 0.73, 0.73));
-    let green = Lambertian::new(Color::new(0.12, 0.45, 0.15));
-    let light = DiffuseLight::new(Color::new(15.0, 15.0, 15.0));
+    let red = file.add_material(
+        "red",
+        MaterialDesc::lambertian(Color::new(0.65, 0.05, 0.05)),
+    );
+    let white = file.add_material(
+        "white",
+        MaterialDesc::lambertian(Color::new(0.73, 0.73, 0.73)),
+    );
+    let green = file.add_material(
+        "green",
+        MaterialDesc::lambertian(Color::new(0.12, 0.45, 0.15)),
+    );
+    let light = file.add_material(
+        "light",
+        MaterialDesc::diffuse_light(Color::new(15.0, 15.0, 15.0)),
+    );
 
-    world.add(Quad::new(
+    file.add(ObjectDesc::quad(
         Point3::new(555.0, 0.0, 0.0),
         Vec3A::Y * 555.0,
         Vec3A::Z * 555.0,
-        green,
+        &green,
     ));
-    world.add(Quad::new(
+    file.add(ObjectDesc::quad(
         Point3::new(0.0, 0.0, 0.0),
         Vec3A::Y * 555.0,
         Vec3A::Z * 555.0,
-        red,
+        &red,
     ));
-    world.add(Quad::new(
+    file.add(ObjectDesc::quad(
         Point3::new(343.0, 554.0, 332.0),
         Vec3A::X * -130.0,
         Vec3A::Z * -105.0,
-        light,
+        &light,
     ));
-    world.add(Quad::new(
+    file.add(ObjectDesc::quad(
         Point3::new(0.0, 0.0, 0.0),
         Vec3A::X * 555.0,
         Vec3A::Z * 555.0,
-        white.clone(),
+        &white,
     ));
-    world.add(Quad::new(
+    file.add(ObjectDesc::quad(
         Point3::new(555.0, 555.0, 555.0),
         Vec3A::X * -555.0,
         Vec3A::Z * -555.0,
-        white.clone(),
+        &white,
     ));
-    world.add(Quad::new(
+    file.add(ObjectDesc::quad(
         Point3::new(0.0, 0.0, 555.0),
         Vec3A::X * 555.0,
         Vec3A::Y * 555.0,
-        white.clone(),
+        &white,
     ));
 
-    let box_1 = new_box(
-        Point3::ZERO,
-        Point3::new(165.0, 330.0, 165.0),
-        white.clone(),
-    );
-    let box_1 = Rotation::<AXIS_Y>::new(box_1, 15.0);
-    let box_1 = Translate::new(box_1, Vec3A::new(265.0, 0.0, 295.0));
-    world.add(box_1);
-
-    let box_2 = new_box(
-        Point3::ZERO,
-        Point3::new(165.0, 165.0, 165.0),
-        white.clone(),
-    );
-    let box_2 = Rotation::<AXIS_Y>::new(box_2, -18.0);
-    let box_2 = Translate::new(box_2, Vec3A::new(130.0, 0.0, 65.0));
-    world.add(box_2);
+    let box_1 = ObjectDesc::new_box(Point3::ZERO, Point3::new(165.0, 330.0, 165.0), &white);
+    file.add(ObjectDesc::instance(
+        box_1,
+        15.0,
+        Vec3A::new(265.0, 0.0, 295.0),
+    ));
 
-    let mut camera = Camera::new();
+    let box_2 = ObjectDesc::new_box(Point3::ZERO, Point3::new(165.0, 165.0, 165.0), &white);
+    file.add(ObjectDesc::instance(
+        box_2,
+        -18.0,
+        Vec3A::new(130.0, 0.0, 65.0),
+    ));
 
-    camera.aspect_ratio = 1.0;
-    camera.image_width = 600;
-    camera.samples_per_pixel = 200;
-    camera.max_depth = 50;
-    camera.background = Color::ZERO;
+    file.camera = CameraDesc {
+        aspect_ratio: 1.0,
+        image_width: 600,
+        samples_per_pixel: 200,
+        max_depth: 50,
+        background: Color::ZERO,
 
-    camera.vfov = 40.0;
-    camera.lookfrom = Point3::new(278.0, 278.0, -800.0);
-    camera.lookat = Point3::new(278.0, 278.0, 0.0);
-    camera.vup = Vec3A::Y;
+        vfov: 40.0,
+        lookfrom: Point3::new(278.0, 278.0, -800.0),
+        lookat: Point3::new(278.0, 278.0, 0.0),
+        vup: Vec3A::Y,
 
-    camera.defocus_angle = 0.0;
+        defocus_angle: 0.0,
+        ..Default::default()
+    };
 
-    camera.render(&world)
+    Scene::new(file)
 }
 
-pub fn cornell_smoke() -> Result<()> {
-    let mut world = HittableList::new();
+pub fn cornell_box(overrides: &CameraOverrides) -> Result<()> {
+    render(build_cornell_box()?, overrides)
+}
 
-    let red = Lambertian::new(Color::new(0.65, 0.05, 0.05));
-    let white = Lambertian::new(Color::new(0.73, 0.73, 0.73));
-    let green = Lambertian::new(Color::new(0.12, 0.45, 0.15));
-    let light = DiffuseLight::new(Color::new(7.0, 7.0, 7.0));
+pub fn build_cornell_smoke() -> Result<Scene> {
+    let mut file = SceneFile {
+        metadata: metadata(
+            "cornell_smoke",
+            "The Cornell box with boxes of black and white smoke.",
+        ),
+        ..Default::default()
+    };
+
+    let red = file.add_material(
+        "red",
+        MaterialDesc::lambertian(Color::new(0.65, 0.05, 0.05)),
+    );
+    let white = file.add_material(
+        "white",
+        MaterialDesc::lambertian(Color::new(0.73, 0.73, 0.73)),
+    );
+    let green = file.add_material(
+        "green",
+        MaterialDesc::lambertian(Color::new(0.12, 0.45, 0.15)),
+    );
+    let light = file.add_material(
+        "light",
+        MaterialDesc::diffuse_light(Color::new(7.0, 7.0, 7.0)),
+    );
 
-    world.add(Quad::new(
+    file.add(ObjectDesc::quad(
         Point3::X * 555.0,
         Vec3A::Y * 555.0,
         Vec3A::Z * 555.0,
-        green,
+        &green,
     ));
-    world.add(Quad::new(
+    file.add(ObjectDesc::quad(
         Point3::ZERO,
         Vec3A::Y * 555.0,
         Vec3A::Z * 555.0,
-        red,
+        &red,
     ));
-    world.add(Quad::new(
+    file.add(ObjectDesc::quad(
         Point3::new(113.0, 554.0, 127.0),
         Vec3A::X * 330.0,
         Vec3A::Z * 305.0,
-        light,
+        &light,
     ));
-    world.add(Quad::new(
+    file.add(ObjectDesc::quad(
         Point3::Y * 555.0,
         Vec3A::X * 555.0,
         Vec3A::Z * 555.0,
-        white.clone(),
+        &white,
     ));
-    world.add(Quad::new(
+    file.add(ObjectDesc::quad(
         Point3::ZERO,
         Vec3A::X * 555.0,
         Vec3A::Z * 555.0,
-        white.clone(),
+        &white,
     ));
-    world.add(Quad::new(
+    file.add(ObjectDesc::quad(
         Point3::Z * 555.0,
         Vec3A::X * 555.0,
         Vec3A::Y * 555.0,
-        white.clone(),
+        &white,
     ));
 
-    let box_1 = new_box(
-        Point3::ZERO,
-        Point3::new(165.0, 330.0, 165.0),
-        white.clone(),
-    );
-    let box_1 = Rotation::<AXIS_Y>::new(box_1, 15.0);
-    let box_1 = Translate::new(box_1, Vec3A::new(265.0, 0.0, 295.0));
+    let box_1 = ObjectDesc::new_box(Point3::ZERO, Point3::new(165.0, 330.0, 165.0), &white);
+    let box_1 = ObjectDesc::instance(box_1, 15.0, Vec3A::new(265.0, 0.0, 295.0));
 
-    let box_2 = new_box(
-        Point3::ZERO,
-        Point3::new(165.0, 165.0, 165.0),
-        white.clone(),
-    );
-    let box_2 = Rotation::<AXIS_Y>::new(box_2, -18.0);
-    let box_2 = Translate::new(box_2, Vec3A::new(130.0, 0.0, 65.0));
+    let box_2 = ObjectDesc::new_box(Point3::ZERO, Point3::new(165.0, 165.0, 165.0), &white);
+    let box_2 = ObjectDesc::instance(box_2, -18.0, Vec3A::new(130.0, 0.0, 65.0));
 
-    world.add(ConstantMedium::new(box_1, 0.01, Color::ZERO));
-    world.add(ConstantMedium::new(box_2, 0.01, Color::ONE));
+    file.add(ObjectDesc::constant_medium(box_1, 0.01, Color::ZERO));
+    file.add(ObjectDesc::constant_medium(box_2, 0.01, Color::ONE));
 
-    let mut camera = Camera::new();
+    file.camera = CameraDesc {
+        aspect_ratio: 1.0,
+        image_width: 600,
+        samples_per_pixel: 200,
+        max_depth: 50,
+        background: Color::ZERO,
 
-    camera.aspect_ratio = 1.0;
-    camera.image_width = 600;
-    camera.samples_per_pixel = 200;
-    camera.max_depth = 50;
-    camera.background = Color::ZERO;
+        vfov: 40.0,
+        lookfrom: Point3::new(278.0, 278.0, -800.0),
+        lookat: Point3::new(278.0, 278.0, 0.0),
+        vup: Vec3A::Y,
 
-    camera.vfov = 40.0;
-    camera.lookfrom = Point3::new(278.0, 278.0, -800.0);
-    camera.lookat = Point3::new(278.0, 278.0, 0.0);
-    camera.vup = Vec3A::Y;
+        defocus_angle: 0.0,
+        ..Default::default()
+    };
 
-    camera.defocus_angle = 0.0;
+    Scene::new(file)
+}
 
-    camera.render(&world)
+pub fn cornell_smoke(overrides: &CameraOverrides) -> Result<()> {
+    render(build_cornell_smoke()?, overrides)
 }
 
-pub fn next_weeks_final_scene(
+pub fn build_next_weeks_final_scene(
     image_width: i32,
     samples_per_pixel: i32,
     max_depth: i32,
-) -> Result<()> {
-    let mut boxes_1 = HittableList::new();
-    let ground = Lambertian::new(Color::new(0.48, 0.83, 0.53));
+) -> Result<Scene> {
+    random::seed(crate::SEED);
+    let mut file = SceneFile {
+        metadata: metadata(
+            "next_weeks_final_scene",
+            "The final scene of Ray Tracing: The Next Week, with every feature of the book.",
+        ),
+        ..Default::default()
+    };
+
+    let ground = file.add_material(
+        "ground",
+        MaterialDesc::lambertian(Color::new(0.48, 0.83, 0.53)),
+    );
 
+    let mut boxes_1 = vec![];
     let boxes_per_side = 20;
     for i in 0..boxes_per_side {
         for j in 0..boxes_per_side {
@@ -493,107 +674,148 @@ pub fn next_weeks_final_scene(
             let y1 = random_f32(1.0, 101.0);
             let z1 = z0 + w;
 
-            boxes_1.add(new_box(
+            boxes_1.push(ObjectDesc::new_box(
                 Point3::new(x0, y0, z0),
                 Point3::new(x1, y1, z1),
-                ground.clone(),
+                &ground,
             ));
         }
     }
+    file.add(ObjectDesc::group(boxes_1));
 
-    let mut world = HittableList::new();
-    world.add(BVHNode::from_list(&boxes_1));
-
-    let light = DiffuseLight::new(Color::new(7.0, 7.0, 7.0));
-    world.add(Quad::new(
+    let light = file.add_material(
+        "light",
+        MaterialDesc::diffuse_light(Color::new(7.0, 7.0, 7.0)),
+    );
+    file.add(ObjectDesc::quad(
         Point3::new(123.0, 554.0, 147.0),
         Vec3A::X * 300.0,
         Vec3A::Z * 265.0,
-        light,
+        &light,
     ));
 
     let center1 = Point3::new(400.0, 400.0, 200.0);
     let center2 = center1 + Vec3A::X * 30.0;
-    let sphere_material = Lambertian::new(Color::new(0.7, 0.3, 0.1));
-    world.add(Sphere::new_moving(center1, center2, 50.0, sphere_material));
+    let sphere_material = file.add_material(
+        "moving",
+        MaterialDesc::lambertian(Color::new(0.7, 0.3, 0.1)),
+    );
+    file.add(ObjectDesc::moving_sphere(
+        center1,
+        center2,
+        50.0,
+        &sphere_material,
+    ));
 
-    world.add(Sphere::new_stationary(
+    let glass = file.add_material("glass", MaterialDesc::dielectric(1.5));
+    file.add(ObjectDesc::sphere(
         Point3::new(260.0, 150.0, 45.0),
         50.0,
-        Dielectric::new(1.5),
+        &glass,
     ));
-    world.add(Sphere::new_stationary(
+    let metal = file.add_material("metal", MaterialDesc::metal(Color::new(0.8, 0.8, 0.9), 1.0));
+    file.add(ObjectDesc::sphere(
         Point3::new(0.0, 150.0, 145.0),
         50.0,
-        Metal::new(Color::new(0.8, 0.8, 0.9), 1.0),
+        &metal,
     ));
 
-    let boundary =
-        Sphere::new_stationary(Point3::new(360.0, 150.0, 145.0), 70.0, Dielectric::new(1.5));
-    world.add(boundary.clone());
-    world.add(ConstantMedium::new(
+    let boundary = ObjectDesc::sphere(Point3::new(360.0, 150.0, 145.0), 70.0, &glass);
+    file.add(boundary.clone());
+    file.add(ObjectDesc::constant_medium(
         boundary,
         0.2,
         Color::new(0.2, 0.4, 0.9),
     ));
-    let boundary = Sphere::new_stationary(Point3::ZERO, 5000.0, Dielectric::new(1.5));
-    world.add(ConstantMedium::new(boundary, 0.0001, Color::ONE));
+    let boundary = ObjectDesc::sphere(Point3::ZERO, 5000.0, &glass);
+    file.add(ObjectDesc::constant_medium(boundary, 0.0001, Color::ONE));
 
-    let emat = Lambertian::new(image::open("assets/textures/earthmap.jpg").unwrap());
-    world.add(Sphere::new_stationary(
+    let earth_texture = file.add_texture(
+        "earthmap",
+        TextureDesc::Image {
+            path: "assets/textures/earthmap.jpg".into(),
+        },
+    );
+    let emat = file.add_material("earth", MaterialDesc::lambertian(earth_texture));
+    file.add(ObjectDesc::sphere(
         Point3::new(400.0, 200.0, 400.0),
         100.0,
-        emat,
+        &emat,
     ));
-    let pertext = NoiseTexture::scaled(0.1);
-    world.add(Sphere::new_stationary(
+    let pertext = file.add_texture("noise", TextureDesc::Noise { scale: 0.1 });
+    let pertext = file.add_material("noise", MaterialDesc::lambertian(pertext));
+    file.add(ObjectDesc::sphere(
         Point3::new(220.0, 280.0, 300.0),
         80.0,
-        Lambertian::new(pertext),
+        &pertext,
     ));
 
-    let mut boxes_2 = HittableList::new();
-    let white = Lambertian::new(Color::new(0.73, 0.73, 0.73));
+    let white = file.add_material(
+        "white",
+        MaterialDesc::lambertian(Color::new(0.73, 0.73, 0.73)),
+    );
     let ns = 1000;
-    for _ in 0..ns {
-        boxes_2.add(Sphere::new_stationary(
-            random_vec_in_range(0.0, 165.0),
-            10.0,
-            white.clone(),
-        ));
-    }
+    let boxes_2 = (0..ns)
+        .map(|_| ObjectDesc::sphere(random_vec_in_range(0.0, 165.0), 10.0, &white))
+        .collect();
 
-    world.add(Translate::new(
-        Rotation::<AXIS_Y>::new(BVHNode::from_list(&boxes_2), 15.0),
+    file.add(ObjectDesc::instance(
+        ObjectDesc::group(boxes_2),
+        15.0,
         Vec3A::new(-100.0, 270.0, 395.0),
     ));
 
-    let mut camera = Camera::new();
+    file.camera = CameraDesc {
+        aspect_ratio: 1.0,
+        image_width,
+        samples_per_pixel,
+        max_depth,
+        background: Color::ZERO,
+
+        vfov: 40.0,
+        lookfrom: Point3::new(478.0, 278.0, -600.0),
+        lookat: Point3::new(278.0, 278.0, 0.0),
+        vup: Vec3A::Y,
+
+        defocus_angle: 0.0,
+        ..Default::default()
+    };
 
-    camera.aspect_ratio = 1.0;
-    camera.image_width = image_width;
-    camera.samples_per_pixel = samples_per_pixel;
-    camera.max_depth = max_depth;
-    camera.background = Color::ZERO;
+    Scene::new(file)
+}
+
+pub fn next_weeks_final_scene(
+    image_width: i32,
+    samples_per_pixel: i32,
+    max_depth: i32,
+    overrides: &CameraOverrides,
+) -> Result<()> {
+    render(
+        build_next_weeks_final_scene(image_width, samples_per_pixel, max_depth)?,
+        overrides,
+    )
+}
 
-    camera.vfov = 40.0;
-    camera.lookfrom = Point3::new(478.0, 278.0, -600.0);
-    camera.lookat = Point3::new(278.0, 278.0, 0.0);
-    camera.vup = Vec3A::Y;
+pub fn build_next_weeks_final_scene_low_res() -> Result<Scene> {
+    build_next_weeks_final_scene(400, 250, 4)
+}
 
-    camera.defocus_angle = 0.0;
+pub fn next_weeks_final_scene_low_res(overrides: &CameraOverrides) -> Result<()> {
+    render(build_next_weeks_final_scene_low_res()?, overrides)
+}
 
-    camera.render(&world)
+pub fn build_next_weeks_final_scene_mid_res() -> Result<Scene> {
+    build_next_weeks_final_scene(600, 500, 8)
 }
 
-pub fn next_weeks_final_scene_low_res() -> Result<()> {
-    next_weeks_final_scene(400, 250, 4)
+pub fn next_weeks_final_scene_mid_res(overrides: &CameraOverrides) -> Result<()> {
+    render(build_next_weeks_final_scene_mid_res()?, overrides)
 }
 
-pub fn next_weeks_final_scene_mid_res() -> Result<()> {
-    next_weeks_final_scene(600, 500, 8)
+pub fn build_next_weeks_final_scene_high_res() -> Result<Scene> {
+    build_next_weeks_final_scene(800, 10000, 40)
 }
 
-pub fn next_weeks_final_scene_high_res() -> Result<()> {
-    next_weeks_final_scene(800, 10000, 40)
+pub fn next_weeks_final_scene_high_res(overrides: &CameraOverrides) -> Result<()> {
+    render(build_next_weeks_final_scene_high_res()?, overrides)
 }
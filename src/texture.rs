@@ -1,16 +1,106 @@
 use std::sync::Arc;
 
-use crate::util::{color::Color, perlin::Perlin, Point3};
-use image::{DynamicImage, GenericImageView};
+use crate::{
+    hittable::HitRecord,
+    util::{color::Color, perlin::Perlin, Point3},
+};
+use image::{DynamicImage, GenericImageView, Rgb32FImage};
 
 pub trait Texture: Send + Sync {
     fn sample(&self, u: f32, v: f32, point: Point3) -> Color;
+
+    /// The color at a hit, for textures that need more of it than its UVs and point.
+    fn sample_hit(&self, rec: &HitRecord) -> Color {
+        self.sample(rec.u, rec.v, rec.p)
+    }
+
+    /// Whether the texture is known to be black everywhere.
+    fn is_black(&self) -> bool {
+        false
+    }
+}
+
+impl<T: Texture + ?Sized> Texture for Arc<T> {
+    fn sample(&self, u: f32, v: f32, point: Point3) -> Color {
+        (**self).sample(u, v, point)
+    }
+
+    fn sample_hit(&self, rec: &HitRecord) -> Color {
+        (**self).sample_hit(rec)
+    }
+
+    fn is_black(&self) -> bool {
+        (**self).is_black()
+    }
 }
 
 impl Texture for Color {
     fn sample(&self, _u: f32, _v: f32, _point: Point3) -> Color {
         *self
     }
+
+    fn is_black(&self) -> bool {
+        *self == Color::ZERO
+    }
+}
+
+/// A texture with its colors multiplied by `scale`.
+pub struct ScaledTexture {
+    pub texture: Arc<dyn Texture>,
+    pub scale: Color,
+}
+
+impl ScaledTexture {
+    pub fn new(texture: impl Texture + 'static, scale: Color) -> Self {
+        Self {
+            texture: Arc::new(texture),
+            scale,
+        }
+    }
+}
+
+impl Texture for ScaledTexture {
+    fn sample(&self, u: f32, v: f32, point: Point3) -> Color {
+        self.scale * self.texture.sample(u, v, point)
+    }
+
+    fn sample_hit(&self, rec: &HitRecord) -> Color {
+        self.scale * self.texture.sample_hit(rec)
+    }
+
+    fn is_black(&self) -> bool {
+        self.scale == Color::ZERO || self.texture.is_black()
+    }
+}
+
+/// The colors of the vertices of a [`TriangleMesh`](crate::hittable::mesh::TriangleMesh),
+/// blended across each face it is hit on.
+///
+/// Anywhere else, such as on other objects, it is white.
+pub struct VertexColors {
+    pub colors: Arc<[Color]>,
+}
+
+impl VertexColors {
+    pub fn new(colors: Arc<[Color]>) -> Self {
+        Self { colors }
+    }
+}
+
+impl Texture for VertexColors {
+    fn sample(&self, _u: f32, _v: f32, _point: Point3) -> Color {
+        Color::ONE
+    }
+
+    fn sample_hit(&self, rec: &HitRecord) -> Color {
+        match rec.vertices {
+            Some((indices, weights)) => {
+                let [a, b, c] = indices.map(|i| self.colors[i as usize]);
+                weights.x * a + weights.y * b + weights.z * c
+            }
+            None => Color::ONE,
+        }
+    }
 }
 
 /// A Spatial Texture; Does not map to non-cartesian texture coordinate spaces.
@@ -67,8 +157,68 @@ impl Texture for DynamicImage {
     }
 }
 
+/// How texture coordinates outside `[0, 1]` are brought into an image.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Wrap {
+    /// To the nearest edge.
+    #[default]
+    Clamp,
+    /// The image tiles the plane.
+    Repeat,
+    /// The image tiles the plane, with every other copy mirrored.
+    MirroredRepeat,
+}
+
+impl Wrap {
+    fn apply(self, t: f32) -> f32 {
+        match self {
+            Wrap::Clamp => t.clamp(0.0, 1.0),
+            Wrap::Repeat => t.rem_euclid(1.0),
+            Wrap::MirroredRepeat => 1.0 - (t.rem_euclid(2.0) - 1.0).abs(),
+        }
+    }
+}
+
+/// An image of linear colors, which many textures can share, looked up at the nearest pixel.
+pub struct ImageTexture {
+    pub image: Arc<Rgb32FImage>,
+    pub wrap_u: Wrap,
+    pub wrap_v: Wrap,
+}
+
+impl ImageTexture {
+    pub fn new(image: Arc<Rgb32FImage>, wrap_u: Wrap, wrap_v: Wrap) -> Self {
+        Self {
+            image,
+            wrap_u,
+            wrap_v,
+        }
+    }
+}
+
+impl Texture for ImageTexture {
+    fn sample(&self, u: f32, v: f32, _point: Point3) -> Color {
+        let (width, height) = self.image.dimensions();
+        if width == 0 || height == 0 {
+            return Color::ZERO;
+        }
+
+        let u = self.wrap_u.apply(u);
+        let v = 1.0 - self.wrap_v.apply(v);
+        let i = ((u * width as f32) as u32).min(width - 1);
+        let j = ((v * height as f32) as u32).min(height - 1);
+        Color::from(self.image.get_pixel(i, j).0)
+    }
+}
+
 pub struct NoiseTexture(Perlin, f32);
 
+impl Default for NoiseTexture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl NoiseTexture {
     pub fn new() -> Self {
         Self(Perlin::new(), 1.0)
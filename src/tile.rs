@@ -0,0 +1,161 @@
+//! Splitting an image into tiles, the unit of work when rendering.
+
+use std::str::FromStr;
+
+use anyhow::{bail, Error};
+
+/// A rectangle of pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Tile {
+    pub fn pixels(&self) -> u32 {
+        self.width * self.height
+    }
+}
+
+/// The order tiles are handed out to the render threads in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TileOrder {
+    /// Row by row from the top left.
+    #[default]
+    Scanline,
+    /// Outwards from the center of the image.
+    Spiral,
+    /// Along a Hilbert curve, so consecutive tiles are mostly neighbours. They always are when
+    /// the grid is a square with a power of two tiles on a side; otherwise the curve of the next
+    /// larger such grid is followed, and it jumps over the tiles that are outside the image.
+    Hilbert,
+}
+
+impl FromStr for TileOrder {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "scanline" => TileOrder::Scanline,
+            "spiral" => TileOrder::Spiral,
+            "hilbert" => TileOrder::Hilbert,
+            _ => bail!("unknown tile order `{s}`, expected scanline, spiral or hilbert"),
+        })
+    }
+}
+
+/// A `width` by `height` image cut into tiles of at most `size` by `size` pixels.
+#[derive(Debug, Clone, Copy)]
+pub struct TileGrid {
+    pub width: u32,
+    pub height: u32,
+    pub size: u32,
+}
+
+impl TileGrid {
+    pub fn new(width: u32, height: u32, size: u32) -> Self {
+        Self {
+            width,
+            height,
+            size: size.max(1),
+        }
+    }
+
+    pub fn columns(&self) -> u32 {
+        self.width.div_ceil(self.size)
+    }
+
+    pub fn rows(&self) -> u32 {
+        self.height.div_ceil(self.size)
+    }
+
+    /// The tile in column `tx` and row `ty` of the grid.
+    pub fn tile(&self, tx: u32, ty: u32) -> Tile {
+        let (x, y) = (tx * self.size, ty * self.size);
+        Tile {
+            x,
+            y,
+            width: self.size.min(self.width - x),
+            height: self.size.min(self.height - y),
+        }
+    }
+
+    /// Grid indices, `ty * columns + tx`, of every tile in the given order.
+    pub fn order(&self, order: TileOrder) -> Vec<usize> {
+        let (columns, rows) = (self.columns(), self.rows());
+        let mut indices = (0..(columns * rows) as usize).collect::<Vec<_>>();
+        let coords = |i: usize| (i as u32 % columns, i as u32 / columns);
+
+        match order {
+            TileOrder::Scanline => {}
+            TileOrder::Spiral => {
+                let center = (columns as f32 / 2.0 - 0.5, rows as f32 / 2.0 - 0.5);
+                let key = |i: usize| {
+                    let (tx, ty) = coords(i);
+                    let (dx, dy) = (tx as f32 - center.0, ty as f32 - center.1);
+                    let ring = dx.abs().max(dy.abs()).round() as u32;
+                    (ring, dy.atan2(dx))
+                };
+                indices.sort_by(|&a, &b| {
+                    let (a, b) = (key(a), key(b));
+                    a.0.cmp(&b.0).then(a.1.total_cmp(&b.1))
+                });
+            }
+            TileOrder::Hilbert => {
+                let n = columns.max(rows).next_power_of_two();
+                indices.sort_by_key(|&i| {
+                    let (tx, ty) = coords(i);
+                    hilbert_index(n, tx, ty)
+                });
+            }
+        }
+
+        indices
+    }
+
+    /// Splits per-pixel data, `per_pixel` elements for each pixel row by row, into the parts
+    /// that belong to each tile, one slice per row of the tile, indexed like [`TileGrid::order`].
+    pub fn split<'a, T>(&self, data: &'a mut [T], per_pixel: usize) -> Vec<Vec<&'a mut [T]>> {
+        let columns = self.columns() as usize;
+        let mut tiles = (0..columns * self.rows() as usize)
+            .map(|_| vec![])
+            .collect::<Vec<_>>();
+
+        let row_len = self.width as usize * per_pixel;
+        let segment_len = self.size as usize * per_pixel;
+        for (y, row) in data.chunks_mut(row_len).enumerate() {
+            let ty = y / self.size as usize;
+            for (tx, segment) in row.chunks_mut(segment_len).enumerate() {
+                tiles[ty * columns + tx].push(segment);
+            }
+        }
+
+        tiles
+    }
+}
+
+/// Position of `(x, y)` along a Hilbert curve filling an `n` by `n` grid, `n` a power of two.
+fn hilbert_index(n: u32, mut x: u32, mut y: u32) -> u64 {
+    let mut d = 0;
+    let mut s = n / 2;
+    while s > 0 {
+        let rx = (x & s > 0) as u32;
+        let ry = (y & s > 0) as u32;
+        d += s as u64 * s as u64 * ((3 * rx) ^ ry) as u64;
+
+        // Rotate the quadrant so the curve inside it lines up with its neighbours.
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - (x & (s - 1));
+                y = s - 1 - (y & (s - 1));
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        x &= s - 1;
+        y &= s - 1;
+        s /= 2;
+    }
+    d
+}
@@ -1,20 +1,141 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Error};
+
 pub type Color = glam::Vec3A;
 
+/// Relative luminance of a linear color.
+pub fn luminance(c: Color) -> f32 {
+    c.dot(Color::new(0.2126, 0.7152, 0.0722))
+}
+
+/// Compresses linear radiance into the displayable `[0, 1]` range.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMap {
+    /// Clips everything above 1.0.
+    #[default]
+    Clamp,
+    /// `c / (1 + c)` per channel.
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve.
+    AcesFilmic,
+}
+
+impl ToneMap {
+    pub fn apply(self, c: f32) -> f32 {
+        let c = c.max(0.0);
+        match self {
+            ToneMap::Clamp => c.min(1.0),
+            ToneMap::Reinhard => c / (1.0 + c),
+            ToneMap::AcesFilmic => {
+                let (a, b, c2, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+                ((c * (a * c + b)) / (c * (c2 * c + d) + e)).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+impl FromStr for ToneMap {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "clamp" => ToneMap::Clamp,
+            "reinhard" => ToneMap::Reinhard,
+            "aces" | "aces-filmic" => ToneMap::AcesFilmic,
+            _ => bail!("unknown tone map `{s}`, expected clamp, reinhard or aces"),
+        })
+    }
+}
+
+/// Encodes a tone mapped value for a display.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Transfer {
+    /// A plain gamma of 2.0.
+    #[default]
+    Gamma2,
+    /// The piecewise sRGB curve.
+    Srgb,
+    /// No encoding at all.
+    Linear,
+}
+
+impl Transfer {
+    pub fn apply(self, c: f32) -> f32 {
+        match self {
+            Transfer::Gamma2 => linear_to_gamma(c),
+            Transfer::Srgb => {
+                if c <= 0.003_130_8 {
+                    12.92 * c
+                } else {
+                    1.055 * c.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            Transfer::Linear => c,
+        }
+    }
+
+    /// The inverse of [`Transfer::apply`], from encoded values back to linear ones.
+    pub fn decode(self, c: f32) -> f32 {
+        match self {
+            Transfer::Gamma2 => c * c,
+            Transfer::Srgb => {
+                if c <= 0.040_45 {
+                    c / 12.92
+                } else {
+                    ((c + 0.055) / 1.055).powf(2.4)
+                }
+            }
+            Transfer::Linear => c,
+        }
+    }
+}
+
+impl FromStr for Transfer {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "gamma2" => Transfer::Gamma2,
+            "srgb" => Transfer::Srgb,
+            "linear" => Transfer::Linear,
+            _ => bail!("unknown transfer function `{s}`, expected gamma2, srgb or linear"),
+        })
+    }
+}
+
+/// Turns linear radiance into 8-bit display values.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DisplayTransform {
+    /// Exposure adjustment in stops, applied before tone mapping.
+    pub exposure: f32,
+    pub tone_map: ToneMap,
+    pub transfer: Transfer,
+}
+
+impl DisplayTransform {
+    pub fn apply(&self, linear: Color) -> [u8; 3] {
+        let scaled = linear * self.exposure.exp2();
+        scaled
+            .to_array()
+            .map(|c| quantize(self.transfer.apply(self.tone_map.apply(c))))
+    }
+}
+
 fn linear_to_gamma(linear_component: f32) -> f32 {
     linear_component.sqrt()
 }
 
+fn quantize(c: f32) -> u8 {
+    (256.0 * c.clamp(0.0, 0.999)) as u8
+}
+
 pub fn convert_color(pixel_color: Color, samples_per_pixel: i32) -> [u8; 3] {
     let scale = 1.0 / samples_per_pixel as f32;
-
-    let r = (256.0 * linear_to_gamma(pixel_color.x * scale).clamp(0.0, 0.999)) as u8;
-    let g = (256.0 * linear_to_gamma(pixel_color.y * scale).clamp(0.0, 0.999)) as u8;
-    let b = (256.0 * linear_to_gamma(pixel_color.z * scale).clamp(0.0, 0.999)) as u8;
-
-    [r, g, b]
+    DisplayTransform::default().apply(pixel_color * scale)
 }
 
 pub fn write_color(out: &mut impl std::io::Write, pixel_color: Color, samples_per_pixel: i32) {
     let rgb = convert_color(pixel_color, samples_per_pixel);
-    write!(out, "{} {} {}\n", rgb[0], rgb[1], rgb[2]).expect("Failed to write color!");
+    writeln!(out, "{} {} {}", rgb[0], rgb[1], rgb[2]).expect("Failed to write color!");
 }
@@ -1,4 +1,4 @@
-use std::{f32::INFINITY, ops::Add};
+use std::ops::Add;
 
 #[derive(Debug, Clone, Copy)]
 pub struct Interval {
@@ -7,7 +7,7 @@ pub struct Interval {
 }
 
 impl Interval {
-    pub const UNIVERSE: Self = Interval::new(-INFINITY, INFINITY);
+    pub const UNIVERSE: Self = Interval::new(f32::NEG_INFINITY, f32::INFINITY);
 
     pub const fn new(min: f32, max: f32) -> Self {
         Self { min, max }
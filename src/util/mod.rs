@@ -1,6 +1,8 @@
 pub mod all;
 pub mod color;
 pub mod interval;
+pub mod onb;
+pub mod pdf;
 pub mod perlin;
 pub mod random;
 pub mod ray;
@@ -2,15 +2,22 @@ use std::f32::consts::PI;
 
 use glam::Vec3A;
 
-use crate::hittable::Hittable;
+use crate::{
+    hittable::Hittable,
+    sampler::{sample_cosine_direction, sample_unit_vector, Sampler},
+};
 
-use super::{onb::ONB, random::random_unit_vector, vec::random_cosine_direction, Point3};
+use super::{onb::ONB, Point3};
 
+/// A distribution of directions.
 pub trait PDF {
+    /// Density, per unit solid angle, of `direction`.
     fn value(&self, direction: Vec3A) -> f32;
-    fn generate(&self) -> Vec3A;
+    /// A direction drawn from the distribution.
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3A;
 }
 
+/// Every direction with the same density.
 pub struct SpherePDF;
 
 impl PDF for SpherePDF {
@@ -18,11 +25,12 @@ impl PDF for SpherePDF {
         1.0 / (4.0 * PI)
     }
 
-    fn generate(&self) -> Vec3A {
-        random_unit_vector()
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3A {
+        sample_unit_vector(sampler.get_2d())
     }
 }
 
+/// Directions around `w` with a density proportional to the cosine of the angle to it.
 pub struct CosinePDF {
     pub uvw: ONB,
 }
@@ -41,12 +49,12 @@ impl PDF for CosinePDF {
         (cosine_theta / PI).max(0.0)
     }
 
-    fn generate(&self) -> Vec3A {
-        self.uvw.local(random_cosine_direction())
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3A {
+        self.uvw.local(sample_cosine_direction(sampler.get_2d()))
     }
 }
 
-// Hittable PDF
+/// Directions from `origin` towards `objects`.
 pub struct HittablePDF<'a> {
     pub objects: &'a dyn Hittable,
     pub origin: Point3,
@@ -58,12 +66,59 @@ impl<'a> HittablePDF<'a> {
     }
 }
 
-impl<'a> PDF for HittablePDF<'a> {
+impl PDF for HittablePDF<'_> {
     fn value(&self, direction: Vec3A) -> f32 {
         self.objects.pdf_value(self.origin, direction)
     }
 
-    fn generate(&self) -> Vec3A {
-        self.objects.random(self.origin)
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3A {
+        self.objects.random(self.origin, sampler)
+    }
+}
+
+/// Picks either of two distributions with equal chance.
+pub struct MixturePDF<'a> {
+    pub p: [&'a dyn PDF; 2],
+}
+
+impl<'a> MixturePDF<'a> {
+    pub fn new(p0: &'a dyn PDF, p1: &'a dyn PDF) -> Self {
+        Self { p: [p0, p1] }
+    }
+}
+
+impl PDF for MixturePDF<'_> {
+    fn value(&self, direction: Vec3A) -> f32 {
+        0.5 * self.p[0].value(direction) + 0.5 * self.p[1].value(direction)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3A {
+        if sampler.get_1d() < 0.5 {
+            self.p[0].generate(sampler)
+        } else {
+            self.p[1].generate(sampler)
+        }
+    }
+}
+
+/// The distributions materials scatter light with.
+pub enum ScatterPDF {
+    Cosine(CosinePDF),
+    Sphere(SpherePDF),
+}
+
+impl PDF for ScatterPDF {
+    fn value(&self, direction: Vec3A) -> f32 {
+        match self {
+            ScatterPDF::Cosine(p) => p.value(direction),
+            ScatterPDF::Sphere(p) => p.value(direction),
+        }
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3A {
+        match self {
+            ScatterPDF::Cosine(p) => p.generate(sampler),
+            ScatterPDF::Sphere(p) => p.generate(sampler),
+        }
     }
 }
@@ -1,7 +1,11 @@
 use glam::Vec3A;
-use rand::{seq::SliceRandom, thread_rng};
+use rand::seq::SliceRandom;
 
-use super::{hermitian_smoothing, random::random_vec_in_range, Point3};
+use super::{
+    hermitian_smoothing,
+    random::{random_vec_in_range, with_rng},
+    Point3,
+};
 
 const POINT_COUNT: usize = 256;
 
@@ -12,21 +16,23 @@ pub struct Perlin {
     perm_z: [i32; POINT_COUNT],
 }
 
+impl Default for Perlin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Perlin {
     pub fn new() -> Self {
-        let mut ranvec = [Vec3A::ZERO; POINT_COUNT];
-        for i in 0..POINT_COUNT {
-            ranvec[i] = random_vec_in_range(-1.0, 1.0).normalize();
-        }
-
         Self {
-            ranvec,
+            ranvec: std::array::from_fn(|_| random_vec_in_range(-1.0, 1.0).normalize()),
             perm_x: Self::generate_perm(),
             perm_y: Self::generate_perm(),
             perm_z: Self::generate_perm(),
         }
     }
 
+    #[allow(clippy::needless_range_loop)]
     pub fn noise(&self, p: Point3) -> f32 {
         let u = p.x - p.x.floor();
         let v = p.y - p.y.floor();
@@ -65,18 +71,13 @@ impl Perlin {
     }
 
     fn generate_perm() -> [i32; POINT_COUNT] {
-        let mut p = [0; POINT_COUNT];
-
-        for i in 0..POINT_COUNT {
-            p[i] = i as i32;
-        }
-
-        p.shuffle(&mut thread_rng());
+        let mut p = std::array::from_fn(|i| i as i32);
+        with_rng(|rng| p.shuffle(rng));
 
         p
     }
 
-    #[allow(unused)]
+    #[allow(unused, clippy::needless_range_loop)]
     fn trilinear_interp(c: [[[f32; 2]; 2]; 2], u: f32, v: f32, w: f32) -> f32 {
         let mut acc = 0.0;
         for i in 0..2 {
@@ -92,6 +93,7 @@ impl Perlin {
         acc
     }
 
+    #[allow(clippy::needless_range_loop)]
     fn perlin_interp(c: [[[Vec3A; 2]; 2]; 2], u: f32, v: f32, w: f32) -> f32 {
         let uu = hermitian_smoothing(u);
         let vv = hermitian_smoothing(v);
@@ -1,17 +1,44 @@
+//! Random numbers for building and rendering scenes.
+//!
+//! Every draw comes from a per-thread generator that is reseeded before anything that has to be
+//! reproducible, so results never depend on which thread did the work.
+
+use std::cell::RefCell;
+
 use glam::Vec3A;
-use rand::{distributions::Uniform, prelude::Distribution, thread_rng};
+use rand::{distributions::Uniform, prelude::Distribution, SeedableRng};
+use rand_pcg::Pcg32;
 
-pub fn random_f32s<const N: usize>(min: f32, max: f32) -> [f32; N] {
-    let uniform = Uniform::from(min..max);
-    let mut rng = thread_rng();
+thread_local! {
+    static RNG: RefCell<Pcg32> = RefCell::new(Pcg32::seed_from_u64(crate::SEED));
+}
 
-    let mut out = [0.0; N];
+/// Restarts the random numbers of the current thread from `seed`.
+pub fn seed(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = Pcg32::seed_from_u64(seed));
+}
 
-    for i in 0..N {
-        out[i] = uniform.sample(&mut rng);
-    }
+/// Runs `f` with the generator of the current thread.
+pub fn with_rng<R>(f: impl FnOnce(&mut Pcg32) -> R) -> R {
+    RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
+
+/// The seed for the random numbers of one sample of one pixel.
+pub fn sample_seed(seed: u64, pixel: u64, sample: u64) -> u64 {
+    mix64(mix64(mix64(seed) ^ pixel) ^ sample)
+}
 
-    out
+/// A good 64-bit mixing function, from SplitMix64.
+pub(crate) fn mix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+pub fn random_f32s<const N: usize>(min: f32, max: f32) -> [f32; N] {
+    let uniform = Uniform::from(min..max);
+    with_rng(|rng| std::array::from_fn(|_| uniform.sample(rng)))
 }
 
 pub fn random_f32(min: f32, max: f32) -> f32 {
@@ -0,0 +1,20 @@
+use anyhow::Result;
+use ray_tracing::scenes;
+
+/// The same seed gives the same image however many threads render it.
+#[test]
+fn render_is_independent_of_thread_count() -> Result<()> {
+    let render = |threads: usize| -> Result<Vec<_>> {
+        let mut scene = scenes::build_cornell_box()?;
+        scene.camera.image_width = 16;
+        scene.camera.samples_per_pixel = 4;
+        scene.camera.tile_size = 4;
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()?;
+        Ok(pool.install(|| scene.render())?.pixels)
+    };
+
+    assert_eq!(render(1)?, render(4)?);
+    Ok(())
+}